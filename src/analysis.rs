@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use pnet::util::MacAddr;
+use crate::report::{HostSource, ScanReport};
+
+/// Number of addresses one MAC may answer for before it is flagged by default.
+pub const DEFAULT_SHARED_MAC_THRESHOLD: usize = 4;
+
+/// A MAC that answered for more addresses than expected of a single host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedMac {
+    pub mac: MacAddr,
+    /// Addresses the MAC claimed, in ascending order.
+    pub ips: Vec<Ipv4Addr>,
+    /// Whether the MAC belongs to the gateway, which points to proxy ARP on the
+    /// router rather than a spoofer.
+    pub is_gateway: bool,
+    /// Label of the MAC, when label lookup is enabled and it is listed.
+    pub label: Option<String>,
+}
+
+/// Groups the addresses of a report by MAC and returns the MACs that claimed
+/// more than `threshold` of them, most addresses first.
+///
+/// Every MAC of an address conflict counts, not only the one in the host table,
+/// and the local machine is left out. The gateway's MAC is the one the host
+/// table lists for `gateway`, so a spoofer that also claims the gateway's
+/// address is not mistaken for it.
+pub fn shared_macs(report: &ScanReport, threshold: usize, gateway: Option<Ipv4Addr>) -> Vec<SharedMac> {
+    let gateway_mac = gateway.and_then(|gateway| report.hosts.iter().find(|host| host.ip == gateway))
+        .map(|host| host.mac);
+
+    let mut by_mac: HashMap<MacAddr, Vec<Ipv4Addr>> = HashMap::new();
+    for host in report.hosts.iter().filter(|host| host.source != HostSource::Local) {
+        by_mac.entry(host.mac).or_default().push(host.ip);
+    }
+    for conflict in &report.conflicts {
+        // The first MAC is already in the host table
+        for sighting in conflict.macs.iter().skip(1) {
+            by_mac.entry(sighting.mac).or_default().push(conflict.ip);
+        }
+    }
+
+    let mut shared: Vec<SharedMac> = by_mac.into_iter()
+        .filter(|(_, ips)| ips.len() > threshold)
+        .map(|(mac, mut ips)| {
+            ips.sort_unstable();
+            ips.dedup();
+            SharedMac {
+                mac,
+                is_gateway: gateway_mac == Some(mac),
+                label: report.hosts.iter().find(|host| host.mac == mac).and_then(|host| host.label.clone()),
+                ips,
+            }
+        })
+        .collect();
+    shared.sort_by(|a, b| b.ips.len().cmp(&a.ips.len()).then_with(|| a.mac.cmp(&b.mac)));
+    shared
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+    use super::*;
+    use crate::report::{AddressConflict, HostEntry, MacSighting};
+    use crate::targets::TargetSet;
+
+    const GATEWAY: Ipv4Addr = Ipv4Addr::new(10, 1, 0, 1);
+
+    fn ip(last: u8) -> Ipv4Addr {
+        Ipv4Addr::new(10, 1, 0, last)
+    }
+
+    fn mac(last: u8) -> MacAddr {
+        MacAddr::new(0x02, 0, 0, 0, 0x10, last)
+    }
+
+    fn host(ip: Ipv4Addr, mac: MacAddr, source: HostSource) -> HostEntry {
+        HostEntry {
+            ip,
+            mac,
+            label: None,
+            hostname: None,
+            ipv6: Vec::new(),
+            interface: None,
+            rounds: None,
+            source,
+            latency: None,
+        }
+    }
+
+    /// A conflict over `ip` between `first`, listed in the host table, and `second`.
+    fn conflict(ip: Ipv4Addr, first: MacAddr, second: MacAddr) -> AddressConflict {
+        let sighting = |mac| MacSighting { mac, first_seen: UNIX_EPOCH, last_seen: UNIX_EPOCH, frames: 1 };
+        AddressConflict { ip, macs: vec![sighting(first), sighting(second)] }
+    }
+
+    fn report(hosts: Vec<HostEntry>, conflicts: Vec<AddressConflict>) -> ScanReport {
+        ScanReport {
+            interface: None,
+            local_ip: None,
+            targets: TargetSet::new(),
+            hosts,
+            ipv6_only: Vec::new(),
+            conflicts,
+            anomalies: Vec::new(),
+            started: UNIX_EPOCH,
+            duration: Duration::ZERO,
+        }
+    }
+
+    /// Hosts at `first..first + count`, all answering from `mac`.
+    fn hosts_of(mac: MacAddr, first: u8, count: u8) -> Vec<HostEntry> {
+        (first..first + count).map(|last| host(ip(last), mac, HostSource::Reply)).collect()
+    }
+
+    #[test]
+    fn flags_macs_above_the_threshold() {
+        let mut hosts = hosts_of(mac(4), 10, 4);
+        hosts.extend(hosts_of(mac(5), 20, 5));
+        let shared = shared_macs(&report(hosts, Vec::new()), 4, None);
+
+        assert_eq!(shared, vec![SharedMac {
+            mac: mac(5),
+            ips: (20..25).map(ip).collect(),
+            is_gateway: false,
+            label: None,
+        }]);
+    }
+
+    #[test]
+    fn leaves_out_the_local_machine() {
+        let hosts = (10..20).map(|last| host(ip(last), mac(1), HostSource::Local)).collect();
+        assert_eq!(shared_macs(&report(hosts, Vec::new()), 4, None), vec![]);
+    }
+
+    #[test]
+    fn counts_every_mac_of_a_conflict() {
+        let mut hosts = hosts_of(mac(9), 10, 3);
+        hosts.extend([host(ip(30), mac(30), HostSource::Reply), host(ip(31), mac(31), HostSource::Reply)]);
+        let conflicts = vec![conflict(ip(30), mac(30), mac(9)), conflict(ip(31), mac(31), mac(9))];
+        let shared = shared_macs(&report(hosts, conflicts), 4, None);
+
+        assert_eq!(shared.len(), 1);
+        assert_eq!(shared[0].mac, mac(9));
+        assert_eq!(shared[0].ips, vec![ip(10), ip(11), ip(12), ip(30), ip(31)]);
+    }
+
+    #[test]
+    fn tells_proxy_arp_on_the_gateway_from_a_spoofer() {
+        // The gateway answers for the addresses behind it
+        let mut hosts = vec![host(GATEWAY, mac(1), HostSource::Reply)];
+        hosts.extend(hosts_of(mac(1), 10, 5));
+        let shared = shared_macs(&report(hosts, Vec::new()), 4, Some(GATEWAY));
+        assert_eq!(shared.len(), 1);
+        assert!(shared[0].is_gateway);
+
+        // A spoofer claims the gateway's address after the gateway answered for it
+        let mut hosts = vec![host(GATEWAY, mac(1), HostSource::Reply)];
+        hosts.extend(hosts_of(mac(66), 10, 4));
+        let shared = shared_macs(&report(hosts, vec![conflict(GATEWAY, mac(1), mac(66))]), 4, Some(GATEWAY));
+        assert_eq!(shared.len(), 1);
+        assert_eq!(shared[0].mac, mac(66));
+        assert_eq!(shared[0].ips[0], GATEWAY);
+        assert!(!shared[0].is_gateway);
+    }
+
+    #[test]
+    fn sorts_by_number_of_addresses() {
+        let mut hosts = hosts_of(mac(5), 10, 5);
+        hosts.extend(hosts_of(mac(7), 20, 7));
+        hosts.extend(hosts_of(mac(6), 30, 6));
+        let shared = shared_macs(&report(hosts, Vec::new()), 4, None);
+
+        let macs: Vec<MacAddr> = shared.iter().map(|shared| shared.mac).collect();
+        assert_eq!(macs, vec![mac(7), mac(6), mac(5)]);
+    }
+}
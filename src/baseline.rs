@@ -0,0 +1,355 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use pnet::packet::arp::{ArpOperations, ArpPacket};
+use pnet::packet::ethernet::{EtherTypes, EthernetPacket};
+use pnet::packet::Packet;
+use pnet::util::MacAddr;
+use crate::labels;
+use crate::report::{mac_string, HostEntry, ScanReport};
+use crate::scanner::ArpScanner;
+use crate::Result;
+
+/// How long after a request a reply to it is still expected.
+const REPLY_WINDOW: Duration = Duration::from_secs(5);
+
+/// How many outstanding requests to remember for matching replies.
+const MAX_PENDING_REQUESTS: usize = 4096;
+
+/// Trusted IP to MAC pairs to compare live ARP traffic against.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Baseline {
+    hosts: HashMap<Ipv4Addr, MacAddr>,
+}
+
+impl Baseline {
+    /// An empty baseline.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trusts the hosts of a scan as they are.
+    pub fn from_hosts(hosts: &[HostEntry]) -> Self {
+        Self { hosts: hosts.iter().map(|host| (host.ip, host.mac)).collect() }
+    }
+
+    /// Reads a snapshot with an address and a MAC at the start of each line,
+    /// such as the saved output of a scan. Further columns, lines starting with
+    /// an IPv6 address and text after `#` are ignored.
+    pub fn read_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        let mut baseline = Self::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut fields = line.split_whitespace();
+            let Some(ip) = fields.next() else {
+                continue;
+            };
+            if Ipv6Addr::from_str(ip).is_ok() {
+                continue;
+            }
+            let invalid = |what: &str, value: &str| format!("{}:{}: invalid {} '{}'", path.display(), number + 1, what, value);
+            let ip = Ipv4Addr::from_str(ip).map_err(|_| invalid("address", ip))?;
+            let mac = fields.next().unwrap_or_default();
+            let mac = MacAddr::from_str(mac).map_err(|_| invalid("MAC", mac))?;
+            baseline.insert(ip, mac);
+        }
+        Ok(baseline)
+    }
+
+    /// Reads the addresses given in `labels.txt` as `MAC=LABEL=HOSTNAME=IP`.
+    pub fn from_labels() -> Result<Self> {
+        let mut baseline = Self::new();
+        for (ip, mac) in labels::load_label_addresses()? {
+            baseline.insert(ip, mac);
+        }
+        Ok(baseline)
+    }
+
+    /// Trusts `mac` for `ip`, replacing any earlier MAC.
+    pub fn insert(&mut self, ip: Ipv4Addr, mac: MacAddr) {
+        self.hosts.insert(ip, mac);
+    }
+
+    /// Adds the pairs of `other`, which win over pairs for the same address.
+    pub fn extend(&mut self, other: Baseline) {
+        self.hosts.extend(other.hosts);
+    }
+
+    /// The trusted MAC of `ip`.
+    pub fn get(&self, ip: Ipv4Addr) -> Option<MacAddr> {
+        self.hosts.get(&ip).copied()
+    }
+
+    /// Number of trusted addresses.
+    pub fn len(&self) -> usize {
+        self.hosts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hosts.is_empty()
+    }
+}
+
+/// Suspicious ARP traffic seen while watching against a [`Baseline`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ArpAlert {
+    /// A trusted address was claimed by a different MAC.
+    MacChanged { ip: Ipv4Addr, expected: MacAddr, seen: MacAddr },
+    /// A gratuitous ARP claimed the gateway's address from a MAC other than the
+    /// trusted one, or while the gateway is not in the baseline.
+    GatewayClaimed { ip: Ipv4Addr, mac: MacAddr, expected: Option<MacAddr> },
+    /// A reply arrived that no request seen on the network asked for.
+    UnsolicitedReply { ip: Ipv4Addr, mac: MacAddr, target_ip: Ipv4Addr },
+}
+
+/// One tab-separated line per alert: the kind of alert, the address, and the
+/// MACs involved.
+impl fmt::Display for ArpAlert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArpAlert::MacChanged { ip, expected, seen } => write!(f, "mac-changed\t{}\t{} -> {}",
+                ip, mac_string(*expected), mac_string(*seen)),
+            ArpAlert::GatewayClaimed { ip, mac, expected: Some(expected) } => write!(f, "gateway-claimed\t{}\t{} -> {}",
+                ip, mac_string(*expected), mac_string(*mac)),
+            ArpAlert::GatewayClaimed { ip, mac, expected: None } => write!(f, "gateway-claimed\t{}\t{}",
+                ip, mac_string(*mac)),
+            ArpAlert::UnsolicitedReply { ip, mac, target_ip } => write!(f, "unsolicited-reply\t{}\t{}\tto {}",
+                ip, mac_string(*mac), target_ip),
+        }
+    }
+}
+
+/// Checks ARP frames against a baseline and reports every distinct alert once.
+#[derive(Debug, Clone)]
+pub struct ArpMonitor {
+    baseline: Baseline,
+    gateway: Option<Ipv4Addr>,
+    /// Requests seen recently, by requesting and requested address.
+    pending: HashMap<(Ipv4Addr, Ipv4Addr), SystemTime>,
+    reported: HashSet<ArpAlert>,
+}
+
+impl ArpMonitor {
+    pub fn new(baseline: Baseline, gateway: Option<Ipv4Addr>) -> Self {
+        Self { baseline, gateway, pending: HashMap::new(), reported: HashSet::new() }
+    }
+
+    pub fn baseline(&self) -> &Baseline {
+        &self.baseline
+    }
+
+    pub fn gateway(&self) -> Option<Ipv4Addr> {
+        self.gateway
+    }
+
+    /// Checks one Ethernet frame seen at `seen` and returns the alerts it raises
+    /// that have not been reported before.
+    pub fn inspect(&mut self, frame: &[u8], seen: SystemTime) -> Vec<ArpAlert> {
+        let Some(ethernet) = EthernetPacket::new(frame) else {
+            return Vec::new();
+        };
+        if ethernet.get_ethertype() != EtherTypes::Arp {
+            return Vec::new();
+        }
+        let Some(arp) = ArpPacket::new(ethernet.payload()) else {
+            return Vec::new();
+        };
+        let sender_ip = arp.get_sender_proto_addr();
+        let sender_mac = arp.get_sender_hw_addr();
+        let target_ip = arp.get_target_proto_addr();
+
+        // ARP probes use an unspecified sender address
+        if sender_ip.is_unspecified() {
+            return Vec::new();
+        }
+
+        let mut alerts = Vec::new();
+        let gratuitous = sender_ip == target_ip;
+        let expected = self.baseline.get(sender_ip);
+        if gratuitous && Some(sender_ip) == self.gateway && expected != Some(sender_mac) {
+            alerts.push(ArpAlert::GatewayClaimed { ip: sender_ip, mac: sender_mac, expected });
+        } else if let Some(expected) = expected.filter(|expected| *expected != sender_mac) {
+            alerts.push(ArpAlert::MacChanged { ip: sender_ip, expected, seen: sender_mac });
+        }
+
+        match arp.get_operation() {
+            ArpOperations::Request if !gratuitous => {
+                if self.pending.len() >= MAX_PENDING_REQUESTS {
+                    self.forget_old_requests(seen);
+                }
+                self.pending.insert((sender_ip, target_ip), seen);
+            }
+            ArpOperations::Reply if !gratuitous => {
+                // A request is answered once, so a second reply to it is unsolicited
+                let solicited = self.pending.remove(&(target_ip, sender_ip))
+                    .is_some_and(|asked| seen.duration_since(asked).unwrap_or_default() < REPLY_WINDOW);
+                if !solicited {
+                    alerts.push(ArpAlert::UnsolicitedReply { ip: sender_ip, mac: sender_mac, target_ip });
+                }
+            }
+            _ => {}
+        }
+
+        alerts.retain(|alert| self.reported.insert(alert.clone()));
+        alerts
+    }
+
+    /// Drops requests whose reply window has passed, and if that leaves too
+    /// many, the older half of the rest.
+    fn forget_old_requests(&mut self, now: SystemTime) {
+        self.pending.retain(|_, asked| now.duration_since(*asked).unwrap_or_default() < REPLY_WINDOW);
+        if self.pending.len() >= MAX_PENDING_REQUESTS {
+            let mut times: Vec<SystemTime> = self.pending.values().copied().collect();
+            let middle = *times.select_nth_unstable(MAX_PENDING_REQUESTS / 2).1;
+            self.pending.retain(|_, asked| *asked > middle);
+        }
+    }
+}
+
+impl ArpScanner {
+    /// Watches ARP traffic without sending anything and calls `on_alert` for
+    /// every new alert `monitor` raises, for `duration` or until the process is
+    /// stopped. Hosts are collected as with [`passive`](ArpScanner::passive).
+    pub fn arp_watch<F>(&self, monitor: &mut ArpMonitor, duration: Option<Duration>, mut on_alert: F) -> Result<ScanReport>
+    where
+        F: FnMut(&ArpAlert),
+    {
+        if self.options.verbose {
+            println!("Watching ARP traffic against {} trusted addresses", monitor.baseline().len());
+            if let Some(gateway) = monitor.gateway() {
+                println!("Gateway: {}", gateway);
+            }
+        }
+        self.listen(duration, |collector, packet, seen| {
+            collector.process_packet(packet, seen);
+            for alert in monitor.inspect(packet, seen) {
+                on_alert(&alert);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+    use super::*;
+    use crate::frames::arp_frame;
+
+    const GATEWAY: Ipv4Addr = Ipv4Addr::new(10, 1, 0, 1);
+
+    fn ip(last: u8) -> Ipv4Addr {
+        Ipv4Addr::new(10, 1, 0, last)
+    }
+
+    fn mac(last: u8) -> MacAddr {
+        MacAddr::new(0x02, 0, 0, 0, 0x10, last)
+    }
+
+    fn at(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    fn monitor(trusted: &[(Ipv4Addr, MacAddr)]) -> ArpMonitor {
+        let mut baseline = Baseline::new();
+        for (ip, mac) in trusted {
+            baseline.insert(*ip, *mac);
+        }
+        ArpMonitor::new(baseline, Some(GATEWAY))
+    }
+
+    fn request(sender_mac: MacAddr, sender_ip: Ipv4Addr, target_ip: Ipv4Addr) -> Vec<u8> {
+        arp_frame(ArpOperations::Request, sender_mac, sender_ip, target_ip)
+    }
+
+    fn reply(sender_mac: MacAddr, sender_ip: Ipv4Addr, target_ip: Ipv4Addr) -> Vec<u8> {
+        arp_frame(ArpOperations::Reply, sender_mac, sender_ip, target_ip)
+    }
+
+    #[test]
+    fn alerts_on_trusted_address_with_new_mac() {
+        let mut monitor = monitor(&[(ip(5), mac(5))]);
+
+        assert_eq!(monitor.inspect(&request(mac(5), ip(5), ip(9)), at(0)), vec![]);
+        assert_eq!(monitor.inspect(&request(mac(66), ip(5), ip(9)), at(1)),
+            vec![ArpAlert::MacChanged { ip: ip(5), expected: mac(5), seen: mac(66) }]);
+        // Addresses outside the baseline may use any MAC
+        assert_eq!(monitor.inspect(&request(mac(66), ip(6), ip(9)), at(2)), vec![]);
+    }
+
+    #[test]
+    fn alerts_on_gateway_claimed_from_another_mac() {
+        let mut monitor = monitor(&[(GATEWAY, mac(1))]);
+
+        assert_eq!(monitor.inspect(&request(mac(1), GATEWAY, GATEWAY), at(0)), vec![]);
+        // Reported as a gateway claim only, not also as a changed MAC
+        assert_eq!(monitor.inspect(&request(mac(66), GATEWAY, GATEWAY), at(1)),
+            vec![ArpAlert::GatewayClaimed { ip: GATEWAY, mac: mac(66), expected: Some(mac(1)) }]);
+    }
+
+    #[test]
+    fn alerts_on_any_claim_of_an_untrusted_gateway() {
+        let mut monitor = monitor(&[]);
+
+        assert_eq!(monitor.inspect(&reply(mac(1), GATEWAY, GATEWAY), at(0)),
+            vec![ArpAlert::GatewayClaimed { ip: GATEWAY, mac: mac(1), expected: None }]);
+        // Other hosts announcing themselves are fine
+        assert_eq!(monitor.inspect(&request(mac(7), ip(7), ip(7)), at(1)), vec![]);
+    }
+
+    #[test]
+    fn matches_replies_to_requests() {
+        let mut answered = monitor(&[]);
+        let unsolicited = ArpAlert::UnsolicitedReply { ip: ip(5), mac: mac(5), target_ip: ip(9) };
+
+        assert_eq!(answered.inspect(&request(mac(9), ip(9), ip(5)), at(0)), vec![]);
+        assert_eq!(answered.inspect(&reply(mac(5), ip(5), ip(9)), at(1)), vec![]);
+        // The request has been answered already
+        assert_eq!(answered.inspect(&reply(mac(5), ip(5), ip(9)), at(2)), vec![unsolicited.clone()]);
+
+        let mut late = monitor(&[]);
+        assert_eq!(late.inspect(&request(mac(9), ip(9), ip(5)), at(0)), vec![]);
+        // Too late for the request
+        assert_eq!(late.inspect(&reply(mac(5), ip(5), ip(9)), at(0) + REPLY_WINDOW), vec![unsolicited]);
+        // Answering a different requester
+        assert_eq!(late.inspect(&reply(mac(5), ip(5), ip(8)), at(6)),
+            vec![ArpAlert::UnsolicitedReply { ip: ip(5), mac: mac(5), target_ip: ip(8) }]);
+    }
+
+    #[test]
+    fn reports_each_alert_once() {
+        let mut monitor = monitor(&[(ip(5), mac(5))]);
+        let changed = |seen| ArpAlert::MacChanged { ip: ip(5), expected: mac(5), seen };
+
+        assert_eq!(monitor.inspect(&request(mac(66), ip(5), ip(9)), at(0)), vec![changed(mac(66))]);
+        assert_eq!(monitor.inspect(&request(mac(66), ip(5), ip(9)), at(1)), vec![]);
+        assert_eq!(monitor.inspect(&request(mac(67), ip(5), ip(9)), at(2)), vec![changed(mac(67))]);
+
+        // A forged reply raises both alerts, each once
+        let forged = reply(mac(66), ip(5), ip(9));
+        assert_eq!(monitor.inspect(&forged, at(3)),
+            vec![ArpAlert::UnsolicitedReply { ip: ip(5), mac: mac(66), target_ip: ip(9) }]);
+        assert_eq!(monitor.inspect(&forged, at(4)), vec![]);
+    }
+
+    #[test]
+    fn remembers_a_bounded_number_of_requests() {
+        let mut monitor = monitor(&[]);
+        for index in 0..3 * MAX_PENDING_REQUESTS as u32 {
+            let sender = Ipv4Addr::from(u32::from(ip(0)) + index);
+            monitor.inspect(&request(mac(9), sender, GATEWAY), at(0) + Duration::from_millis(u64::from(index)));
+        }
+        assert!(monitor.pending.len() <= MAX_PENDING_REQUESTS);
+
+        // The latest requests are still matched
+        let latest = Ipv4Addr::from(u32::from(ip(0)) + 3 * MAX_PENDING_REQUESTS as u32 - 1);
+        assert_eq!(monitor.inspect(&reply(mac(1), GATEWAY, latest), at(13)), vec![]);
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use crate::pcap::PcapWriter;
+use crate::transport::{FrameReceiver, FrameSender};
+use crate::Result;
+
+/// How many sent frames to remember for recognising their loopback copies.
+const MAX_PENDING_ECHOES: usize = 4096;
+
+/// A pcap file shared by the sending and listening halves of a link.
+///
+/// The first write error is reported on stderr and stops further writes, so a
+/// full disk never interrupts the scan itself.
+#[derive(Clone)]
+pub(crate) struct CaptureFile {
+    inner: Arc<Mutex<CaptureState>>,
+}
+
+struct CaptureState {
+    writer: Option<PcapWriter<BufWriter<File>>>,
+    /// Frames we sent that the receiver may see again. Raw sockets on Linux also
+    /// deliver outgoing frames, which would otherwise appear twice in the file.
+    pending_echoes: VecDeque<Vec<u8>>,
+}
+
+impl CaptureState {
+    fn write(&mut self, frame: &[u8]) {
+        if let Some(pcap) = self.writer.as_mut() {
+            if let Err(e) = pcap.write_frame(SystemTime::now(), frame) {
+                eprintln!("Warning: Failed to write pcap file, capture stopped: {}", e);
+                self.writer = None;
+            }
+        }
+    }
+}
+
+impl CaptureFile {
+    pub(crate) fn create(path: &Path) -> Result<Self> {
+        let writer = PcapWriter::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        Ok(Self {
+            inner: Arc::new(Mutex::new(CaptureState {
+                writer: Some(writer),
+                pending_echoes: VecDeque::new(),
+            })),
+        })
+    }
+
+    fn record_sent(&self, frame: &[u8]) {
+        let mut state = self.inner.lock().unwrap();
+        state.write(frame);
+        if state.pending_echoes.len() == MAX_PENDING_ECHOES {
+            state.pending_echoes.pop_front();
+        }
+        state.pending_echoes.push_back(frame.to_vec());
+    }
+
+    fn record_received(&self, frame: &[u8]) {
+        let mut state = self.inner.lock().unwrap();
+        if let Some(position) = state.pending_echoes.iter().position(|sent| sent.as_slice() == frame) {
+            state.pending_echoes.remove(position);
+            return;
+        }
+        state.write(frame);
+    }
+
+    /// Wraps both halves of a link so every frame they carry is also written to the file.
+    pub(crate) fn tee(&self, tx: Box<dyn FrameSender>, rx: Box<dyn FrameReceiver>)
+        -> (Box<dyn FrameSender>, Box<dyn FrameReceiver>) {
+        (
+            Box::new(CaptureSender { inner: tx, capture: self.clone() }),
+            Box::new(CaptureReceiver { inner: rx, capture: self.clone() }),
+        )
+    }
+
+    pub(crate) fn flush(&self) -> io::Result<()> {
+        match self.inner.lock().unwrap().writer.as_mut() {
+            Some(pcap) => pcap.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+struct CaptureSender {
+    inner: Box<dyn FrameSender>,
+    capture: CaptureFile,
+}
+
+impl FrameSender for CaptureSender {
+    fn send_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.capture.record_sent(frame);
+        self.inner.send_frame(frame)
+    }
+}
+
+struct CaptureReceiver {
+    inner: Box<dyn FrameReceiver>,
+    capture: CaptureFile,
+}
+
+impl FrameReceiver for CaptureReceiver {
+    fn recv_frame(&mut self) -> io::Result<Option<&[u8]>> {
+        let frame = self.inner.recv_frame()?;
+        if let Some(frame) = frame {
+            self.capture.record_received(frame);
+        }
+        Ok(frame)
+    }
+}
@@ -0,0 +1,453 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use pnet::packet::arp::{ArpOperations, ArpPacket};
+use pnet::packet::ethernet::{EtherTypes, EthernetPacket};
+use pnet::packet::icmpv6::echo_reply::EchoReplyPacket;
+use pnet::packet::icmpv6::ndp::{NdpOptionTypes, NeighborAdvertPacket};
+use pnet::packet::icmpv6::{Icmpv6Packet, Icmpv6Types};
+use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::ipv6::Ipv6Packet;
+use pnet::packet::Packet;
+use pnet::util::MacAddr;
+use crate::frames;
+use crate::labels::{self, Labels};
+use crate::options::ScanOptions;
+use crate::report::{mac_string, AddressConflict, Anomaly, AnomalyKind, HostEntry, HostSource, Latency, MacSighting, NeighborEntry};
+use crate::targets::TargetSet;
+
+/// What the collector knows about one IP address.
+#[derive(Debug, Clone)]
+struct HostRecord {
+    mac: MacAddr,
+    /// Send round the host first answered in, if it was found by probing.
+    round: Option<u32>,
+    source: HostSource,
+    /// Every MAC seen claiming the address, starting with `mac`.
+    sightings: Vec<MacSighting>,
+    /// Times from request to reply for the replies from `mac`.
+    rtts: Vec<Duration>,
+}
+
+impl HostRecord {
+    fn new(mac: MacAddr, round: Option<u32>, source: HostSource, seen: SystemTime) -> Self {
+        let sighting = MacSighting { mac, first_seen: seen, last_seen: seen, frames: 1 };
+        Self { mac, round, source, sightings: vec![sighting], rtts: Vec::new() }
+    }
+}
+
+/// Builds the host table from ARP frames: replies, and the requests and
+/// gratuitous announcements hosts send on their own.
+///
+/// Clones share the same table, so one can be handed to the listener thread
+/// while the scanner keeps another.
+#[derive(Clone)]
+pub(crate) struct HostCollector {
+    hosts: Arc<Mutex<HashMap<Ipv4Addr, HostRecord>>>,
+    /// Current send round; 0 when not probing (offline captures).
+    round: Arc<AtomicU32>,
+    /// Addresses being probed and how many of them have yet to answer.
+    scope: Option<Arc<TargetSet>>,
+    outstanding: Arc<AtomicU64>,
+    /// Addresses the requests are sent from, which replies must be addressed to.
+    local_ips: Arc<[Ipv4Addr]>,
+    /// Frames that failed validation.
+    anomalies: Arc<Mutex<Vec<Anomaly>>>,
+    /// When the latest request to each target of the current round went out,
+    /// if measuring latency.
+    sent: Option<Arc<Mutex<HashMap<Ipv4Addr, Instant>>>>,
+    /// IPv6 neighbours learned from Neighbor Advertisements and echo replies.
+    neighbors: Arc<Mutex<HashMap<Ipv6Addr, MacAddr>>>,
+    /// Ignore ARP frames other than replies.
+    replies_only: bool,
+    ipv6: bool,
+    verbose: bool,
+    update_labels: bool,
+}
+
+impl HostCollector {
+    pub(crate) fn new(options: &ScanOptions) -> Self {
+        Self {
+            hosts: Arc::new(Mutex::new(HashMap::new())),
+            round: Arc::new(AtomicU32::new(0)),
+            scope: None,
+            outstanding: Arc::new(AtomicU64::new(0)),
+            local_ips: Arc::from(Vec::new()),
+            anomalies: Arc::new(Mutex::new(Vec::new())),
+            sent: options.measure_latency.then(|| Arc::new(Mutex::new(HashMap::new()))),
+            neighbors: Arc::new(Mutex::new(HashMap::new())),
+            replies_only: false,
+            ipv6: options.ipv6,
+            verbose: options.verbose,
+            // Only ensure host entries if lookup is enabled
+            update_labels: options.lookup_labels,
+        }
+    }
+
+    /// Learns hosts from ARP replies only, including gratuitous ones, and not
+    /// from the requests hosts send.
+    pub(crate) fn replies_only(mut self) -> Self {
+        self.replies_only = true;
+        self
+    }
+
+    /// Adds a host that is known without probing, such as the local machine.
+    pub(crate) fn insert(&self, ip: Ipv4Addr, mac: MacAddr) {
+        self.hosts.lock().unwrap().insert(ip, HostRecord::new(mac, None, HostSource::Local, SystemTime::now()));
+    }
+
+    /// Adds an IPv6 neighbour that is known without probing, such as the local machine.
+    pub(crate) fn insert_ipv6(&self, ip: Ipv6Addr, mac: MacAddr) {
+        self.neighbors.lock().unwrap().insert(ip, mac);
+    }
+
+    /// MACs of the IPv4 hosts found so far.
+    pub(crate) fn macs(&self) -> Vec<MacAddr> {
+        let mut macs: Vec<MacAddr> = self.hosts.lock().unwrap().values().map(|record| record.mac).collect();
+        macs.sort_unstable();
+        macs.dedup();
+        macs
+    }
+
+    pub(crate) fn contains(&self, ip: &Ipv4Addr) -> bool {
+        self.hosts.lock().unwrap().contains_key(ip)
+    }
+
+    /// Tracks answers from `targets` to requests sent from `local_ips`, of which
+    /// `outstanding` addresses are still unknown. Replies from elsewhere or to
+    /// other hosts are then recorded as anomalies.
+    pub(crate) fn expect(mut self, targets: TargetSet, local_ips: &[Ipv4Addr], outstanding: u64) -> Self {
+        self.scope = Some(Arc::new(targets));
+        self.local_ips = Arc::from(local_ips);
+        self.outstanding = Arc::new(AtomicU64::new(outstanding));
+        self
+    }
+
+    /// Whether every expected address has answered. Never true while discovering
+    /// IPv6 neighbours, as there is no telling how many will answer, or while
+    /// measuring latency, as every round probes all targets again.
+    pub(crate) fn all_answered(&self) -> bool {
+        self.scope.is_some() && self.outstanding() == 0 && !self.ipv6 && self.sent.is_none()
+    }
+
+    /// Number of expected addresses that have not answered yet.
+    pub(crate) fn outstanding(&self) -> u64 {
+        self.outstanding.load(Ordering::SeqCst)
+    }
+
+    /// Notes that a request to `ip` is about to go out, when measuring latency.
+    pub(crate) fn record_sent(&self, ip: Ipv4Addr) {
+        if let Some(sent) = &self.sent {
+            sent.lock().unwrap().insert(ip, Instant::now());
+        }
+    }
+
+    /// The time since the latest request to `ip`, which a reply to it that has
+    /// just arrived answers. Each request is timed once.
+    fn take_rtt(&self, ip: Ipv4Addr) -> Option<Duration> {
+        let sent = self.sent.as_ref()?.lock().unwrap().remove(&ip)?;
+        Some(sent.elapsed())
+    }
+
+    /// Attributes replies seen from now on to send round `round`. Requests of
+    /// earlier rounds that were never answered are no longer timed.
+    pub(crate) fn start_round(&self, round: u32) {
+        self.round.store(round, Ordering::SeqCst);
+        if let Some(sent) = &self.sent {
+            sent.lock().unwrap().clear();
+        }
+    }
+
+    /// Records the sender of an ARP frame, or with IPv6 enabled, of a Neighbor
+    /// Advertisement or echo reply, seen at `seen`. Returns the address if it is
+    /// a new host.
+    pub(crate) fn process_packet(&self, packet: &[u8], seen: SystemTime) -> Option<IpAddr> {
+        let ethernet = EthernetPacket::new(packet)?;
+        match ethernet.get_ethertype() {
+            EtherTypes::Arp => self.process_arp(&ethernet, seen).map(IpAddr::V4),
+            EtherTypes::Ipv6 if self.ipv6 => self.process_ipv6(&ethernet).map(IpAddr::V6),
+            _ => None,
+        }
+    }
+
+    fn process_arp(&self, ethernet: &EthernetPacket, seen: SystemTime) -> Option<Ipv4Addr> {
+        let arp = ArpPacket::new(ethernet.payload())?;
+        let sender_ip = arp.get_sender_proto_addr();
+        let sender_mac = arp.get_sender_hw_addr();
+
+        // ARP probes use an unspecified sender address
+        if sender_ip.is_unspecified() {
+            return None;
+        }
+        if self.replies_only && arp.get_operation() != ArpOperations::Reply {
+            return None;
+        }
+
+        let source = match arp.get_operation() {
+            _ if arp.get_target_proto_addr() == sender_ip => HostSource::Gratuitous,
+            ArpOperations::Reply => HostSource::Reply,
+            ArpOperations::Request => HostSource::Request,
+            _ => return None,
+        };
+        if let Some(kind) = self.validate(ethernet, &arp, source) {
+            self.record_anomaly(kind, ethernet, &arp, seen);
+            return None;
+        }
+        // Only replies answer a send round
+        let round = match self.round.load(Ordering::SeqCst) {
+            0 => None,
+            _ if source != HostSource::Reply => None,
+            round => Some(round),
+        };
+
+        let rtt = if source == HostSource::Reply { self.take_rtt(sender_ip) } else { None };
+
+        let mut hosts = self.hosts.lock().unwrap();
+        let entry = match hosts.entry(sender_ip) {
+            Entry::Occupied(mut known) => {
+                let record = known.get_mut();
+                if record.mac == sender_mac {
+                    record.rtts.extend(rtt);
+                }
+                self.record_sighting(sender_ip, record, sender_mac, seen);
+                return None;
+            }
+            Entry::Vacant(entry) => entry,
+        };
+        let mut record = HostRecord::new(sender_mac, round, source, seen);
+        record.rtts.extend(rtt);
+        entry.insert(record);
+        if self.scope.as_ref().is_some_and(|scope| scope.contains(sender_ip)) {
+            self.outstanding.fetch_sub(1, Ordering::SeqCst);
+        }
+        if self.verbose {
+            let mac = mac_string(sender_mac);
+            match (source, round) {
+                (HostSource::Reply, Some(round)) if round > 1 => println!("Host {} is up (MAC: {}, round {})",
+                    sender_ip, mac, round),
+                (HostSource::Reply, _) => println!("Host {} is up (MAC: {})", sender_ip, mac),
+                _ => println!("Host {} is up (MAC: {}, via {})", sender_ip, mac, source),
+            }
+        }
+        if self.update_labels {
+            if let Err(e) = labels::ensure_host_entry(sender_mac) {
+                eprintln!("Warning: Failed to update labels.txt: {}", e);
+            }
+        }
+        Some(sender_ip)
+    }
+
+    /// Checks an ARP frame before it is trusted. Every frame must come from the
+    /// MAC it claims, and while scanning, replies must be addressed to one of our
+    /// addresses and come from a probed one. Our own frames are not checked.
+    fn validate(&self, ethernet: &EthernetPacket, arp: &ArpPacket, source: HostSource) -> Option<AnomalyKind> {
+        let sender_ip = arp.get_sender_proto_addr();
+        if self.local_ips.contains(&sender_ip) {
+            return None;
+        }
+        if ethernet.get_source() != arp.get_sender_hw_addr() {
+            return Some(AnomalyKind::SourceMismatch);
+        }
+        let scope = self.scope.as_ref().filter(|_| source == HostSource::Reply)?;
+        if !scope.contains(sender_ip) {
+            return Some(AnomalyKind::OutOfRange);
+        }
+        if !self.local_ips.contains(&arp.get_target_proto_addr()) {
+            return Some(AnomalyKind::NotForUs);
+        }
+        None
+    }
+
+    fn record_anomaly(&self, kind: AnomalyKind, ethernet: &EthernetPacket, arp: &ArpPacket, seen: SystemTime) {
+        let anomaly = Anomaly {
+            kind,
+            sender_ip: arp.get_sender_proto_addr(),
+            sender_mac: arp.get_sender_hw_addr(),
+            ethernet_source: ethernet.get_source(),
+            target_ip: arp.get_target_proto_addr(),
+            first_seen: seen,
+            frames: 1,
+        };
+        let mut anomalies = self.anomalies.lock().unwrap();
+        let same = |known: &&mut Anomaly| known.kind == anomaly.kind && known.sender_ip == anomaly.sender_ip
+            && known.sender_mac == anomaly.sender_mac && known.ethernet_source == anomaly.ethernet_source
+            && known.target_ip == anomaly.target_ip;
+        if let Some(known) = anomalies.iter_mut().find(same) {
+            known.frames += 1;
+            return;
+        }
+        if self.verbose {
+            println!("Ignoring ARP frame: {}", anomaly);
+        }
+        anomalies.push(anomaly);
+    }
+
+    /// Adds another frame claiming a known address, keeping every distinct MAC.
+    fn record_sighting(&self, ip: Ipv4Addr, record: &mut HostRecord, mac: MacAddr, seen: SystemTime) {
+        if let Some(sighting) = record.sightings.iter_mut().find(|sighting| sighting.mac == mac) {
+            sighting.last_seen = sighting.last_seen.max(seen);
+            sighting.frames += 1;
+            return;
+        }
+        if self.verbose {
+            println!("Address conflict: {} is also claimed by {} (first seen from {})",
+                ip, mac_string(mac), mac_string(record.mac));
+        }
+        record.sightings.push(MacSighting { mac, first_seen: seen, last_seen: seen, frames: 1 });
+    }
+
+    /// Learns the target of a Neighbor Advertisement, preferring its target
+    /// link-layer address option, or the source of an echo reply.
+    fn process_ipv6(&self, ethernet: &EthernetPacket) -> Option<Ipv6Addr> {
+        let ipv6 = Ipv6Packet::new(ethernet.payload())?;
+        if ipv6.get_next_header() != IpNextHeaderProtocols::Icmpv6 {
+            return None;
+        }
+        let icmpv6 = Icmpv6Packet::new(ipv6.payload())?;
+
+        let (ip, mac) = match icmpv6.get_icmpv6_type() {
+            Icmpv6Types::NeighborAdvert => {
+                let advert = NeighborAdvertPacket::new(ipv6.payload())?;
+                let mac = advert.get_options().iter()
+                    .find(|option| option.option_type == NdpOptionTypes::TargetLLAddr && option.data.len() >= 6)
+                    .map_or(ethernet.get_source(), |option| {
+                        let data = &option.data;
+                        MacAddr::new(data[0], data[1], data[2], data[3], data[4], data[5])
+                    });
+                (advert.get_target_addr(), mac)
+            }
+            Icmpv6Types::EchoReply => {
+                // Only answers to our own pings, not pings between other hosts
+                let reply = EchoReplyPacket::new(ipv6.payload())?;
+                if reply.get_identifier() != frames::ECHO_IDENTIFIER {
+                    return None;
+                }
+                (ipv6.get_source(), ethernet.get_source())
+            }
+            _ => return None,
+        };
+        if ip.is_unspecified() || ip.is_multicast() {
+            return None;
+        }
+
+        let mut neighbors = self.neighbors.lock().unwrap();
+        if let Entry::Vacant(entry) = neighbors.entry(ip) {
+            entry.insert(mac);
+            if self.verbose {
+                println!("Host {} is up (MAC: {})", ip, mac_string(mac));
+            }
+            return Some(ip);
+        }
+        None
+    }
+
+    /// Turns the collected hosts into report entries sorted by IP, tagged with the
+    /// interface they were seen on.
+    pub(crate) fn host_entries(&self, labels: Option<&Labels>, interface: Option<&str>) -> Vec<HostEntry> {
+        let hosts = self.hosts.lock().unwrap();
+        let neighbors = self.neighbors.lock().unwrap();
+        let mut entries: Vec<HostEntry> = hosts.iter()
+            .map(|(ip, record)| Self::entry(*ip, record, &neighbors, labels, interface))
+            .collect();
+        entries.sort_by_key(|entry| entry.ip.octets());
+        entries
+    }
+
+    /// The report entry for one collected host.
+    pub(crate) fn host_entry(&self, ip: Ipv4Addr, labels: Option<&Labels>, interface: Option<&str>) -> Option<HostEntry> {
+        let hosts = self.hosts.lock().unwrap();
+        let neighbors = self.neighbors.lock().unwrap();
+        hosts.get(&ip).map(|record| Self::entry(ip, record, &neighbors, labels, interface))
+    }
+
+    fn entry(
+        ip: Ipv4Addr,
+        record: &HostRecord,
+        neighbors: &HashMap<Ipv6Addr, MacAddr>,
+        labels: Option<&Labels>,
+        interface: Option<&str>,
+    ) -> HostEntry {
+        let mac_str = mac_string(record.mac);
+        let mut ipv6: Vec<Ipv6Addr> = neighbors.iter()
+            .filter(|(_, mac)| **mac == record.mac)
+            .map(|(ip, _)| *ip)
+            .collect();
+        ipv6.sort_unstable();
+        let (label, hostname) = match labels.and_then(|labels| labels.get(&mac_str)) {
+            Some((label, hostname)) => (Some(label.clone()), hostname.clone()),
+            None => (None, None),
+        };
+        HostEntry {
+            ip,
+            mac: record.mac,
+            label,
+            hostname,
+            ipv6,
+            interface: interface.map(String::from),
+            rounds: record.round,
+            source: record.source,
+            latency: Latency::from_samples(&record.rtts),
+        }
+    }
+
+    /// Addresses claimed by more than one MAC, sorted by address.
+    pub(crate) fn conflicts(&self) -> Vec<AddressConflict> {
+        let hosts = self.hosts.lock().unwrap();
+        let mut conflicts: Vec<AddressConflict> = hosts.iter()
+            .filter(|(_, record)| record.sightings.len() > 1)
+            .map(|(ip, record)| AddressConflict { ip: *ip, macs: record.sightings.clone() })
+            .collect();
+        conflicts.sort_by_key(|conflict| conflict.ip);
+        conflicts
+    }
+
+    /// ARP frames that failed validation, in the order they were first seen.
+    pub(crate) fn anomalies(&self) -> Vec<Anomaly> {
+        self.anomalies.lock().unwrap().clone()
+    }
+
+    /// IPv6 neighbours whose MAC belongs to none of the IPv4 hosts, sorted by address.
+    pub(crate) fn ipv6_only_entries(&self) -> Vec<NeighborEntry> {
+        let hosts = self.hosts.lock().unwrap();
+        let neighbors = self.neighbors.lock().unwrap();
+        let mut entries: Vec<NeighborEntry> = neighbors.iter()
+            .filter(|(_, mac)| !hosts.values().any(|record| record.mac == **mac))
+            .map(|(ip, mac)| NeighborEntry { ip: *ip, mac: *mac })
+            .collect();
+        entries.sort_by_key(|entry| entry.ip);
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An echo reply from `mac` at `ip` carrying `identifier`.
+    fn echo_reply(mac: MacAddr, ip: Ipv6Addr, identifier: u16) -> Vec<u8> {
+        let mut frame = frames::all_nodes_echo(mac, ip, 0);
+        // Type, code and checksum come before the identifier
+        let message = &mut frame[14 + 40..];
+        message[0] = Icmpv6Types::EchoReply.0;
+        message[4..6].copy_from_slice(&identifier.to_be_bytes());
+        frame
+    }
+
+    #[test]
+    fn records_only_replies_to_our_pings() {
+        let collector = HostCollector::new(&ScanOptions::builder().ipv6(true).build());
+        let ours: Ipv6Addr = "fe80::7".parse().unwrap();
+        let theirs: Ipv6Addr = "fe80::8".parse().unwrap();
+
+        let (our_mac, their_mac) = (MacAddr::new(0x02, 0, 0, 0, 0x10, 7), MacAddr::new(0x02, 0, 0, 0, 0x10, 8));
+
+        let seen = SystemTime::now();
+        assert_eq!(collector.process_packet(&echo_reply(our_mac, ours, frames::ECHO_IDENTIFIER), seen), Some(IpAddr::V6(ours)));
+        // A ping between two other hosts, seen in promiscuous mode
+        assert_eq!(collector.process_packet(&echo_reply(their_mac, theirs, 0x1234), seen), None);
+        assert_eq!(collector.ipv6_only_entries(), vec![NeighborEntry { ip: ours, mac: our_mac }]);
+    }
+}
@@ -0,0 +1,159 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+use pnet::packet::arp::{ArpHardwareTypes, ArpOperations, MutableArpPacket};
+use pnet::packet::ethernet::{EtherTypes, MutableEthernetPacket};
+use pnet::packet::icmpv6::echo_request::{self, MutableEchoRequestPacket};
+use pnet::packet::icmpv6::ndp::{self, MutableNeighborSolicitPacket, NdpOption, NdpOptionTypes};
+use pnet::packet::icmpv6::{self, Icmpv6Packet, Icmpv6Types, MutableIcmpv6Packet};
+use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::ipv6::MutableIpv6Packet;
+use pnet::packet::MutablePacket;
+use pnet::util::MacAddr;
+
+/// Length of an Ethernet frame carrying an ARP packet for IPv4.
+pub(crate) const ARP_FRAME_LEN: usize = 42;
+
+/// Builds broadcast ARP requests from one reusable buffer.
+///
+/// Everything but the target address is written once, so producing the frame
+/// for the next target only rewrites four bytes and never allocates.
+pub(crate) struct ArpRequestBuilder {
+    buffer: [u8; ARP_FRAME_LEN],
+}
+
+impl ArpRequestBuilder {
+    pub(crate) fn new(source_mac: MacAddr, source_ip: Ipv4Addr) -> Self {
+        let mut buffer = [0u8; ARP_FRAME_LEN];
+
+        let mut ethernet_packet = MutableEthernetPacket::new(&mut buffer).unwrap();
+        ethernet_packet.set_destination(MacAddr::broadcast());
+        ethernet_packet.set_source(source_mac);
+        ethernet_packet.set_ethertype(EtherTypes::Arp);
+
+        let mut arp_packet = MutableArpPacket::new(ethernet_packet.payload_mut()).unwrap();
+        arp_packet.set_hardware_type(ArpHardwareTypes::Ethernet);
+        arp_packet.set_protocol_type(EtherTypes::Ipv4);
+        arp_packet.set_hw_addr_len(6);
+        arp_packet.set_proto_addr_len(4);
+        arp_packet.set_operation(ArpOperations::Request);
+        arp_packet.set_sender_hw_addr(source_mac);
+        arp_packet.set_sender_proto_addr(source_ip);
+        arp_packet.set_target_hw_addr(MacAddr::zero());
+
+        Self { buffer }
+    }
+
+    /// Returns the request for `target_ip`. The frame is only valid until the next call.
+    pub(crate) fn frame(&mut self, target_ip: Ipv4Addr) -> &[u8] {
+        let mut ethernet_packet = MutableEthernetPacket::new(&mut self.buffer).unwrap();
+        let mut arp_packet = MutableArpPacket::new(ethernet_packet.payload_mut()).unwrap();
+        arp_packet.set_target_proto_addr(target_ip);
+        &self.buffer
+    }
+}
+
+/// Builds a broadcast ARP frame of any kind, for tests that feed frames to the
+/// collector or the monitor directly.
+#[cfg(test)]
+pub(crate) fn arp_frame(operation: pnet::packet::arp::ArpOperation, sender_mac: MacAddr, sender_ip: Ipv4Addr, target_ip: Ipv4Addr) -> Vec<u8> {
+    let mut frame = ArpRequestBuilder::new(sender_mac, sender_ip).frame(target_ip).to_vec();
+    let mut ethernet_packet = MutableEthernetPacket::new(&mut frame).unwrap();
+    MutableArpPacket::new(ethernet_packet.payload_mut()).unwrap().set_operation(operation);
+    frame
+}
+
+/// Ethernet and IPv6 header lengths in front of an ICMPv6 message.
+const IPV6_HEADERS_LEN: usize = 14 + 40;
+/// Neighbor Solicitation with a source link-layer address option.
+const NEIGHBOR_SOLICIT_LEN: usize = 24 + 8;
+/// Echo request without payload.
+const ECHO_REQUEST_LEN: usize = 8;
+
+/// Identifier carried by our ICMPv6 echo requests.
+pub(crate) const ECHO_IDENTIFIER: u16 = 0x6172;
+
+/// The all-nodes link-local multicast address.
+pub(crate) const ALL_NODES: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+
+/// Solicited-node multicast address a Neighbor Solicitation for `target` is sent to.
+fn solicited_node(target: Ipv6Addr) -> Ipv6Addr {
+    let octets = target.octets();
+    Ipv6Addr::from([0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0xff, octets[13], octets[14], octets[15]])
+}
+
+/// Ethernet multicast address that an IPv6 multicast `destination` maps to (RFC 2464).
+fn multicast_mac(destination: Ipv6Addr) -> MacAddr {
+    let octets = destination.octets();
+    MacAddr::new(0x33, 0x33, octets[12], octets[13], octets[14], octets[15])
+}
+
+/// Writes the Ethernet and IPv6 headers for an ICMPv6 message of `icmp_len` bytes
+/// and returns the buffer with the message left zeroed.
+fn icmpv6_frame(source_mac: MacAddr, source_ip: Ipv6Addr, destination: Ipv6Addr, icmp_len: usize) -> Vec<u8> {
+    let mut buffer = vec![0u8; IPV6_HEADERS_LEN + icmp_len];
+
+    let mut ethernet_packet = MutableEthernetPacket::new(&mut buffer).unwrap();
+    ethernet_packet.set_destination(multicast_mac(destination));
+    ethernet_packet.set_source(source_mac);
+    ethernet_packet.set_ethertype(EtherTypes::Ipv6);
+
+    let mut ipv6_packet = MutableIpv6Packet::new(ethernet_packet.payload_mut()).unwrap();
+    ipv6_packet.set_version(6);
+    ipv6_packet.set_payload_length(icmp_len as u16);
+    ipv6_packet.set_next_header(IpNextHeaderProtocols::Icmpv6);
+    // Neighbor Discovery messages must carry the maximum hop limit (RFC 4861)
+    ipv6_packet.set_hop_limit(255);
+    ipv6_packet.set_source(source_ip);
+    ipv6_packet.set_destination(destination);
+
+    buffer
+}
+
+/// Fills in the ICMPv6 checksum of a frame built by [`icmpv6_frame`].
+fn set_icmpv6_checksum(frame: &mut [u8], source_ip: Ipv6Addr, destination: Ipv6Addr) {
+    let message = &mut frame[IPV6_HEADERS_LEN..];
+    let checksum = icmpv6::checksum(&Icmpv6Packet::new(message).unwrap(), &source_ip, &destination);
+    MutableIcmpv6Packet::new(message).unwrap().set_checksum(checksum);
+}
+
+/// Builds a Neighbor Solicitation for `target`, sent to its solicited-node multicast address.
+pub(crate) fn neighbor_solicitation(source_mac: MacAddr, source_ip: Ipv6Addr, target: Ipv6Addr) -> Vec<u8> {
+    let destination = solicited_node(target);
+    let mut frame = icmpv6_frame(source_mac, source_ip, destination, NEIGHBOR_SOLICIT_LEN);
+
+    let mut solicitation = MutableNeighborSolicitPacket::new(&mut frame[IPV6_HEADERS_LEN..]).unwrap();
+    solicitation.set_icmpv6_type(Icmpv6Types::NeighborSolicit);
+    solicitation.set_icmpv6_code(ndp::Icmpv6Codes::NoCode);
+    solicitation.set_target_addr(target);
+    solicitation.set_options(&[NdpOption {
+        option_type: NdpOptionTypes::SourceLLAddr,
+        length: 1,
+        data: source_mac.octets().to_vec(),
+    }]);
+
+    set_icmpv6_checksum(&mut frame, source_ip, destination);
+    frame
+}
+
+/// Builds an echo request to all nodes on the link. Every IPv6 host should answer
+/// it from an address of the same scope as `source_ip`.
+pub(crate) fn all_nodes_echo(source_mac: MacAddr, source_ip: Ipv6Addr, sequence: u16) -> Vec<u8> {
+    let mut frame = icmpv6_frame(source_mac, source_ip, ALL_NODES, ECHO_REQUEST_LEN);
+
+    let mut echo = MutableEchoRequestPacket::new(&mut frame[IPV6_HEADERS_LEN..]).unwrap();
+    echo.set_icmpv6_type(Icmpv6Types::EchoRequest);
+    echo.set_icmpv6_code(echo_request::Icmpv6Codes::NoCode);
+    echo.set_identifier(ECHO_IDENTIFIER);
+    echo.set_sequence_number(sequence);
+
+    set_icmpv6_checksum(&mut frame, source_ip, ALL_NODES);
+    frame
+}
+
+/// The address a host with `mac` forms from `prefix` with a modified EUI-64
+/// interface identifier (RFC 4291), as SLAAC does unless privacy addresses are used.
+pub(crate) fn eui64_address(prefix: Ipv6Addr, mac: MacAddr) -> Ipv6Addr {
+    let mut octets = prefix.octets();
+    let mac = mac.octets();
+    octets[8..].copy_from_slice(&[mac[0] ^ 0x02, mac[1], mac[2], 0xff, 0xfe, mac[3], mac[4], mac[5]]);
+    Ipv6Addr::from(octets)
+}
@@ -0,0 +1,141 @@
+use std::collections::HashSet;
+use std::io;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use crate::report::{mac_string, ScanReport};
+use crate::scanner::ArpScanner;
+use crate::Result;
+
+impl ArpScanner {
+    /// Writes hostnames from `labels.txt` for the hosts in `report` into the Windows hosts file.
+    ///
+    /// Existing entries for the managed IPs and hostnames are replaced; everything else is kept.
+    /// In dummy mode the new entries are printed instead of written.
+    pub fn update_hosts_file(&self, report: &ScanReport) -> Result<()> {
+        // Check if running on Windows
+        if !cfg!(windows) {
+            return Err(Box::new(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "The --add-hosts option is only supported on Windows"
+            )));
+        }
+
+        let hosts_path = Path::new("C:\\Windows\\System32\\drivers\\etc\\hosts");
+        if !hosts_path.exists() {
+            return Err(Box::new(io::Error::new(
+                io::ErrorKind::NotFound,
+                "Windows hosts file not found. Please ensure you're running on Windows."
+            )));
+        }
+
+        let file_content = std::fs::read_to_string(hosts_path)?;
+
+        // Prepare new entries and updates
+        let mut new_entries = String::new();
+
+        // First, collect all IPs and hostnames from the labels that we'll be managing
+        let mut managed_ips = HashSet::new();
+        let mut managed_hostnames = HashSet::new();
+
+        if let Some(labels) = &self.labels {
+            for (mac, (_, hostname)) in labels.iter() {
+                if let Some(hostname) = hostname {
+                    managed_hostnames.insert(hostname.clone());
+                }
+                if let Some(host) = report.hosts.iter().find(|host| mac_string(host.mac) == *mac) {
+                    managed_ips.insert(host.ip);
+                }
+            }
+        }
+
+        // Remove all existing entries that match our managed IPs or hostnames
+        let mut lines: Vec<&str> = file_content.lines().collect();
+        lines.retain(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() >= 2 {
+                // Keep the line if it's not an IP entry, or if its IP and hostname
+                // are not in our managed sets
+                match parts[0].parse::<Ipv4Addr>() {
+                    Ok(ip) => !managed_ips.contains(&ip) && !managed_hostnames.contains(parts[1]),
+                    Err(_) => true,
+                }
+            } else {
+                true
+            }
+        });
+        let file_content = lines.join("\n");
+
+        // Now prepare new entries
+        if self.labels.is_some() {
+            // Hosts in the report are already sorted by IP address
+            let entries: Vec<(Ipv4Addr, &str)> = report.hosts.iter()
+                .filter_map(|host| host.hostname.as_deref().map(|hostname| (host.ip, hostname)))
+                .collect();
+
+            // Calculate the maximum IP width from both existing and new entries
+            let max_ip_width = file_content.lines()
+                .filter_map(|line| {
+                    let parts: Vec<&str> = line.split_whitespace().collect();
+                    if parts.len() >= 2 && parts[0].parse::<Ipv4Addr>().is_ok() {
+                        Some(parts[0].len())
+                    } else {
+                        None
+                    }
+                })
+                .chain(entries.iter().map(|(ip, _)| ip.to_string().len()))
+                .max()
+                .unwrap_or(15);
+
+            // Create the new entries string from sorted entries
+            for (ip, hostname) in entries {
+                new_entries.push_str(&format!("{:<width$}\t\t{}\n", ip, hostname, width = max_ip_width));
+            }
+
+            if !new_entries.is_empty() {
+                if self.options.dummy_mode {
+                    println!("\nEntries to be added:");
+                    println!("----------------------------------------");
+                    print!("{}", new_entries);
+                    println!("----------------------------------------");
+                } else {
+                    // Write back the file with updates
+                    let mut file = std::fs::OpenOptions::new()
+                        .write(true)
+                        .truncate(true)
+                        .open(hosts_path)?;
+
+                    // Normalize existing entries to use two tabs and align IPs
+                    let normalized_content = file_content.lines()
+                        .map(|line| {
+                            let parts: Vec<&str> = line.split_whitespace().collect();
+                            if parts.len() >= 2 && parts[0].parse::<Ipv4Addr>().is_ok() {
+                                format!("{:<width$}\t\t{}", parts[0], parts[1], width = max_ip_width)
+                            } else {
+                                line.to_string()
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+
+                    // Write the existing content first
+                    std::io::Write::write_all(&mut file, normalized_content.as_bytes())?;
+                    if !normalized_content.is_empty() && !normalized_content.ends_with('\n') {
+                        std::io::Write::write_all(&mut file, b"\n")?;
+                    }
+
+                    // Then add the new entries
+                    std::io::Write::write_all(&mut file, new_entries.as_bytes())?;
+
+                    if self.options.verbose {
+                        let new_count = new_entries.lines().count();
+                        println!("Updated hosts file with {} entries", new_count);
+                    }
+                }
+            } else if self.options.dummy_mode {
+                println!("\nNo changes would be made to hosts file.");
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(target_os = "linux")]
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr};
+use ipnetwork::{IpNetwork, Ipv4Network, Ipv6Network};
+use pnet::datalink::{self, NetworkInterface};
+use pnet::util::MacAddr;
+
+/// A network interface as seen by the scanner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceInfo {
+    pub name: String,
+    pub index: u32,
+    pub mac: Option<MacAddr>,
+    pub ipv4: Vec<Ipv4Network>,
+    pub ipv6: Vec<Ipv6Network>,
+    pub is_up: bool,
+    pub is_loopback: bool,
+    pub is_point_to_point: bool,
+    /// Whether an ARP scan can run on the interface: it is up, has a MAC and an
+    /// IPv4 address, and is neither loopback nor point-to-point.
+    pub arp_capable: bool,
+}
+
+impl From<&NetworkInterface> for InterfaceInfo {
+    fn from(interface: &NetworkInterface) -> Self {
+        let mut ipv4 = Vec::new();
+        let mut ipv6 = Vec::new();
+        for network in &interface.ips {
+            match network {
+                IpNetwork::V4(network) => ipv4.push(*network),
+                IpNetwork::V6(network) => ipv6.push(*network),
+            }
+        }
+
+        Self {
+            name: interface.name.clone(),
+            index: interface.index,
+            mac: interface.mac,
+            arp_capable: is_arp_capable(interface),
+            ipv4,
+            ipv6,
+            is_up: interface.is_up(),
+            is_loopback: interface.is_loopback(),
+            is_point_to_point: interface.is_point_to_point(),
+        }
+    }
+}
+
+/// Lists every interface on the system.
+pub fn list_interfaces() -> Vec<InterfaceInfo> {
+    datalink::interfaces().iter().map(InterfaceInfo::from).collect()
+}
+
+pub(crate) fn has_mac(interface: &NetworkInterface) -> bool {
+    interface.mac.is_some_and(|mac| mac != MacAddr::zero())
+}
+
+pub(crate) fn first_ipv4(interface: &NetworkInterface) -> Option<IpAddr> {
+    interface.ips.iter().find(|ip| ip.is_ipv4()).map(|ip| ip.ip())
+}
+
+pub(crate) fn is_arp_capable(interface: &NetworkInterface) -> bool {
+    interface.is_up()
+        && !interface.is_loopback()
+        && !interface.is_point_to_point()
+        && has_mac(interface)
+        && first_ipv4(interface).is_some()
+}
+
+/// The IPv4 default gateway of the interface named `name`, from the kernel
+/// routing table. Only available on Linux.
+#[cfg(target_os = "linux")]
+pub fn default_gateway(name: &str) -> Option<Ipv4Addr> {
+    let routes = fs::read_to_string("/proc/net/route").ok()?;
+    routes.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        // Iface, Destination, Gateway, ... with addresses as hex in memory order
+        if fields.len() < 3 || fields[0] != name || fields[1] != "00000000" {
+            return None;
+        }
+        let gateway = u32::from_str_radix(fields[2], 16).ok()?;
+        Some(Ipv4Addr::from(gateway.to_ne_bytes())).filter(|gateway| !gateway.is_unspecified())
+    })
+}
+
+/// The IPv4 default gateway of the interface named `name`. Only available on Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn default_gateway(_name: &str) -> Option<Ipv4Addr> {
+    None
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+use pnet::util::MacAddr;
+use crate::report::mac_string;
+use crate::Result;
+
+/// Label and optional hostname keyed by uppercase MAC address.
+pub type Labels = HashMap<String, (String, Option<String>)>;
+
+pub(crate) const LABELS_FILE: &str = "labels.txt";
+
+/// Loads `labels.txt` from the working directory. A missing file yields no labels.
+pub fn load_labels() -> Result<Labels> {
+    let mut labels = HashMap::new();
+
+    if !Path::new(LABELS_FILE).exists() {
+        return Ok(labels);
+    }
+
+    let file = File::open(LABELS_FILE)?;
+    for line in io::BufReader::new(file).lines() {
+        let line = line?;
+        let parts: Vec<&str> = line.split('=').collect();
+        if parts.len() >= 2 {
+            let mac = parts[0].trim().to_uppercase();
+            let label = parts[1].trim().to_string();
+            let hostname = if parts.len() >= 3 {
+                Some(parts[2].trim().to_string())
+            } else {
+                None
+            };
+            labels.insert(mac, (label, hostname));
+        }
+    }
+
+    Ok(labels)
+}
+
+/// Loads the addresses given in the optional fourth field of `labels.txt`
+/// (`MAC=LABEL=HOSTNAME=IP`), as trusted IP to MAC pairs. Entries whose MAC or
+/// address cannot be parsed are skipped with a warning.
+pub(crate) fn load_label_addresses() -> Result<Vec<(Ipv4Addr, MacAddr)>> {
+    let mut addresses = Vec::new();
+
+    if !Path::new(LABELS_FILE).exists() {
+        return Ok(addresses);
+    }
+
+    let file = File::open(LABELS_FILE)?;
+    for line in io::BufReader::new(file).lines() {
+        let line = line?;
+        let parts: Vec<&str> = line.split('=').collect();
+        let Some(ip) = parts.get(3).map(|ip| ip.trim()).filter(|ip| !ip.is_empty()) else {
+            continue;
+        };
+        match (MacAddr::from_str(parts[0].trim()), Ipv4Addr::from_str(ip)) {
+            (Ok(mac), Ok(ip)) => addresses.push((ip, mac)),
+            _ => eprintln!("Warning: Ignoring invalid address entry in {}: {}", LABELS_FILE, line),
+        }
+    }
+
+    Ok(addresses)
+}
+
+/// Held while `labels.txt` is rewritten, as scanners on several interfaces
+/// may add entries at the same time.
+static LABELS_FILE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Appends a blank entry for `mac` to `labels.txt` unless it is already listed.
+pub(crate) fn ensure_host_entry(mac: MacAddr) -> Result<()> {
+    let mac_str = mac_string(mac);
+    let _guard = LABELS_FILE_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    // Read existing entries
+    let mut entries = Vec::new();
+    if Path::new(LABELS_FILE).exists() {
+        let file = File::open(LABELS_FILE)?;
+        for line in io::BufReader::new(file).lines() {
+            entries.push(line?);
+        }
+    }
+
+    // Check if MAC already exists
+    if !entries.iter().any(|line| line.starts_with(&mac_str)) {
+        // Add new entry with blank label and hostname
+        entries.push(format!("{}==", mac_str));
+
+        // Write back all entries
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(LABELS_FILE)?;
+
+        for entry in entries {
+            writeln!(file, "{}", entry)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+//! A fast ARP network scanner.
+//!
+//! The [`ArpScanner`] discovers hosts on the local segment by broadcasting ARP
+//! requests and collecting the replies into a [`ScanReport`]:
+//!
+//! ```no_run
+//! use arp_scan::{ArpScanner, ScanOptions};
+//!
+//! let options = ScanOptions::builder().fast_mode(true).build();
+//! let report = ArpScanner::new(options)?.scan()?;
+//! for host in &report.hosts {
+//!     println!("{}\t{}", host.ip, host.mac);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+mod analysis;
+mod baseline;
+mod capture;
+mod collector;
+mod frames;
+mod hosts_file;
+mod interfaces;
+mod labels;
+mod offline;
+mod options;
+mod passive;
+mod pacer;
+mod pcap;
+mod permutation;
+mod report;
+mod scanner;
+mod simulated;
+mod targets;
+mod transport;
+mod watch;
+
+pub use ipnetwork::IpNetwork;
+pub use pnet::util::MacAddr;
+
+pub use analysis::{shared_macs, SharedMac, DEFAULT_SHARED_MAC_THRESHOLD};
+pub use baseline::{ArpAlert, ArpMonitor, Baseline};
+pub use interfaces::{default_gateway, list_interfaces, InterfaceInfo};
+pub use labels::{load_labels, Labels};
+pub use offline::read_capture;
+pub use options::{ScanOptions, ScanOptionsBuilder};
+pub use pcap::{CapturedFrame, PcapReader, PcapWriter};
+pub use report::{mac_string, AddressConflict, Anomaly, AnomalyKind, HostEntry, HostSource, Latency, MacSighting, NeighborEntry, ScanReport};
+pub use scanner::ArpScanner;
+pub use simulated::SimulatedLan;
+pub use targets::TargetSet;
+pub use transport::{FrameReceiver, FrameSender, PnetTransport, Transport};
+pub use watch::{diff_hosts, WatchEvent};
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
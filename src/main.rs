@@ -1,527 +1,274 @@
-use std::net::{IpAddr, Ipv4Addr};
-use std::time::Duration;
-use std::thread;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
 use std::env;
-use std::fs::File;
-use std::io::{self, BufRead, Write};
-use std::path::Path;
-use pnet::datalink::{self, NetworkInterface, Config};
-use pnet::packet::ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket};
-use pnet::packet::arp::{ArpHardwareTypes, ArpOperations, ArpPacket, MutableArpPacket};
-use pnet::packet::{MutablePacket, Packet};
-use pnet::util::MacAddr;
-use ipnetwork::IpNetwork;
-use local_ip_address::local_ip;
+use std::process;
+use std::path::PathBuf;
+use std::time::Duration;
 use std::str::FromStr;
-
-type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
-type DiscoveredHosts = Arc<Mutex<HashMap<Ipv4Addr, MacAddr>>>;
-type Labels = HashMap<String, (String, Option<String>)>;
-
-struct ScanOptions {
-    verbose: bool,
-    fast_mode: bool,
-    custom_range: Option<IpNetwork>,
-    lookup_labels: bool,
-    update_hosts: bool,
-    dummy_mode: bool,
-}
-
-struct ArpScanner {
-    interface: NetworkInterface,
-    local_ip: IpAddr,
-    discovered_hosts: DiscoveredHosts,
-    options: ScanOptions,
-    labels: Option<Labels>,
-}
-
-impl ArpScanner {
-    fn new(options: ScanOptions) -> Result<Self> {
-        let local_ip = local_ip()?;
-        if options.verbose {
-            println!("Local IP address: {}", local_ip);
-            if options.fast_mode {
-                println!("Fast mode enabled - using shorter timeouts");
-            }
+use std::net::Ipv4Addr;
+use arp_scan::{default_gateway, list_interfaces, mac_string, read_capture, shared_macs, ArpMonitor, ArpScanner, Baseline, HostEntry,
+    InterfaceInfo, NeighborEntry, Result, ScanOptions, ScanReport, TargetSet, DEFAULT_SHARED_MAC_THRESHOLD};
+
+/// Exit code when --fail-on-conflict is given and an address conflict was found.
+const CONFLICT_EXIT_CODE: i32 = 2;
+
+fn print_results(hosts: &[HostEntry], show_interface: bool, show_ipv6: bool, show_latency: bool) {
+    // Calculate maximum widths for each column
+    let mut max_ip_width = 15;  // Minimum width for IP
+    let mut max_mac_width = 17;  // Minimum width for MAC
+    let mut max_interface_width = 0;
+    let mut max_ipv6_width = 1;
+    let mut max_latency_width = 1;
+    let mut max_label_width = 0;
+    let mut max_hostname_width = 0;
+
+    // First pass: calculate maximum widths
+    for host in hosts {
+        max_ip_width = max_ip_width.max(host.ip.to_string().len());
+        max_mac_width = max_mac_width.max(mac_string(host.mac).len());
+
+        if let Some(interface) = &host.interface {
+            max_interface_width = max_interface_width.max(interface.len());
         }
-
-        let interface = Self::find_interface(&local_ip)?;
-        if options.verbose {
-            println!("Using interface: {}", interface.name);
+        max_ipv6_width = max_ipv6_width.max(ipv6_column(host).len());
+        max_latency_width = max_latency_width.max(latency_column(host).len());
+        if let Some(label) = &host.label {
+            max_label_width = max_label_width.max(label.len());
+        }
+        if let Some(hostname) = &host.hostname {
+            max_hostname_width = max_hostname_width.max(hostname.len());
         }
-
-        let labels = if options.lookup_labels {
-            Some(Self::load_labels()?)
-        } else {
-            None
-        };
-
-        Ok(Self {
-            interface,
-            local_ip,
-            discovered_hosts: Arc::new(Mutex::new(HashMap::new())),
-            options,
-            labels,
-        })
     }
 
-    fn load_labels() -> Result<Labels> {
-        let mut labels = HashMap::new();
-        
-        if !Path::new("labels.txt").exists() {
-            return Ok(labels);
+    // Print data rows with proper alignment
+    for host in hosts {
+        let mut columns = vec![
+            format!("{:<width$}", host.ip, width = max_ip_width),
+            format!("{:<width$}", mac_string(host.mac), width = max_mac_width),
+        ];
+        if show_interface {
+            columns.push(format!("{:<width$}", host.interface.as_deref().unwrap_or("-"), width = max_interface_width));
         }
-
-        let file = File::open("labels.txt")?;
-        for line in io::BufReader::new(file).lines() {
-            let line = line?;
-            let parts: Vec<&str> = line.split('=').collect();
-            if parts.len() >= 2 {
-                let mac = parts[0].trim().to_uppercase();
-                let label = parts[1].trim().to_string();
-                let hostname = if parts.len() >= 3 {
-                    Some(parts[2].trim().to_string())
-                } else {
-                    None
-                };
-                labels.insert(mac, (label, hostname));
+        if show_ipv6 {
+            columns.push(format!("{:<width$}", ipv6_column(host), width = max_ipv6_width));
+        }
+        if show_latency {
+            columns.push(format!("{:<width$}", latency_column(host), width = max_latency_width));
+        }
+        // If no label or labels not enabled, print without label
+        if let Some(label) = &host.label {
+            if let Some(hostname) = &host.hostname {
+                columns.push(format!("{:<width$}", hostname, width = max_hostname_width));
             }
+            columns.push(format!("{:<width$}", label, width = max_label_width));
         }
-
-        Ok(labels)
+        println!("{}", columns.join("\t"));
     }
+}
 
-    fn find_interface(local_ip: &IpAddr) -> Result<NetworkInterface> {
-        datalink::interfaces()
-            .into_iter()
-            .find(|iface| iface.ips.iter().any(|ip| ip.ip() == *local_ip))
-            .ok_or_else(|| "Failed to find network interface".into())
+fn ipv6_column(host: &HostEntry) -> String {
+    if host.ipv6.is_empty() {
+        "-".to_string()
+    } else {
+        host.ipv6.iter().map(|ip| ip.to_string()).collect::<Vec<_>>().join(",")
     }
+}
 
-    fn create_channel(&self) -> Result<(Box<dyn datalink::DataLinkSender>, Box<dyn datalink::DataLinkReceiver>)> {
-        let config = Config {
-            write_buffer_size: 4096,
-            read_buffer_size: 4096,
-            read_timeout: Some(Duration::from_millis(if self.options.fast_mode { 5 } else { 10 })),
-            write_timeout: None,
-            channel_type: datalink::ChannelType::Layer2,
-            bpf_fd_attempts: 1000,
-            linux_fanout: None,
-            promiscuous: true,
-        };
-
-        match datalink::channel(&self.interface, config) {
-            Ok(datalink::Channel::Ethernet(tx, rx)) => Ok((tx, rx)),
-            _ => Err("Failed to create channel".into()),
-        }
+/// Response times as min/avg/max in milliseconds, like ping.
+fn latency_column(host: &HostEntry) -> String {
+    match &host.latency {
+        Some(latency) => format!("{:.2}/{:.2}/{:.2} ms", latency.min.as_secs_f64() * 1000.0,
+            latency.avg.as_secs_f64() * 1000.0, latency.max.as_secs_f64() * 1000.0),
+        None => "-".to_string(),
     }
+}
 
-    fn create_arp_request(&self, target_ip: Ipv4Addr) -> Result<[u8; 42]> {
-        let source_mac = self.interface.mac.ok_or("No MAC address found for interface")?;
-        let mut buffer = [0u8; 42];
-        
-        if let IpAddr::V4(source_ip) = self.local_ip {
-            let mut ethernet_packet = MutableEthernetPacket::new(&mut buffer).unwrap();
-            ethernet_packet.set_destination(MacAddr::broadcast());
-            ethernet_packet.set_source(source_mac);
-            ethernet_packet.set_ethertype(EtherTypes::Arp);
-
-            let mut arp_buffer = [0u8; 28];
-            let mut arp_packet = MutableArpPacket::new(&mut arp_buffer).unwrap();
-            arp_packet.set_hardware_type(ArpHardwareTypes::Ethernet);
-            arp_packet.set_protocol_type(EtherTypes::Ipv4);
-            arp_packet.set_hw_addr_len(6);
-            arp_packet.set_proto_addr_len(4);
-            arp_packet.set_operation(ArpOperations::Request);
-            arp_packet.set_sender_hw_addr(source_mac);
-            arp_packet.set_sender_proto_addr(source_ip);
-            arp_packet.set_target_hw_addr(MacAddr::zero());
-            arp_packet.set_target_proto_addr(target_ip);
-
-            ethernet_packet.set_payload(arp_packet.packet_mut());
-            Ok(buffer)
-        } else {
-            Err("Local IP is not IPv4".into())
-        }
+/// Prints IPv6 neighbours that did not answer over IPv4, below the other hosts.
+fn print_ipv6_only(neighbors: &[NeighborEntry]) {
+    let ip_width = neighbors.iter().map(|neighbor| neighbor.ip.to_string().len()).max().unwrap_or(0).max(15);
+    for neighbor in neighbors {
+        println!("{:<ip_width$}\t{}", neighbor.ip, mac_string(neighbor.mac), ip_width = ip_width);
     }
+}
 
-    fn start_listener(&self, mut rx: Box<dyn datalink::DataLinkReceiver>) -> thread::JoinHandle<()> {
-        let discovered_hosts = Arc::clone(&self.discovered_hosts);
-        let verbose = self.options.verbose;
-        let fast_mode = self.options.fast_mode;
-        let labels = self.labels.clone();
-        
-        thread::spawn(move || {
-            let start = std::time::Instant::now();
-            let scan_duration = Duration::from_millis(if fast_mode { 500 } else { 2000 });
-
-            if verbose {
-                println!("Started listening for responses...");
-            }
-
-            while start.elapsed() < scan_duration {
-                if let Ok(packet) = rx.next() {
-                    Self::process_packet(&discovered_hosts, packet, verbose, &labels);
-                }
-            }
-
-            let sweep_count = if fast_mode { 5 } else { 10 };
-            for _ in 0..sweep_count {
-                if let Ok(packet) = rx.next() {
-                    Self::process_packet(&discovered_hosts, packet, verbose, &labels);
-                }
-            }
-        })
-    }
-
-    fn process_packet(discovered_hosts: &DiscoveredHosts, packet: &[u8], verbose: bool, labels: &Option<Labels>) {
-        if let Some(ethernet) = EthernetPacket::new(packet) {
-            if ethernet.get_ethertype() == EtherTypes::Arp {
-                if let Some(arp) = ArpPacket::new(ethernet.payload()) {
-                    if arp.get_operation() == ArpOperations::Reply {
-                        let sender_ip = arp.get_sender_proto_addr();
-                        let sender_mac = arp.get_sender_hw_addr();
-                        
-                        let mut hosts = discovered_hosts.lock().unwrap();
-                        if !hosts.contains_key(&sender_ip) {
-                            hosts.insert(sender_ip, sender_mac);
-                            if verbose {
-                                println!("Host {} is up (MAC: {})", sender_ip, sender_mac.to_string().to_uppercase());
-                            }
-                            // Only ensure host entry if lookup is enabled
-                            if labels.is_some() {
-                                if let Err(e) = Self::ensure_host_entry(sender_mac) {
-                                    eprintln!("Warning: Failed to update labels.txt: {}", e);
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+/// Warns about every address claimed by more than one MAC, with the time of each
+/// MAC's first and last frame since the start of the scan. Returns whether there
+/// were any.
+fn print_conflicts(report: &ScanReport) -> bool {
+    let since_start = |time| report.time_since_start(time).as_secs_f64();
+    for conflict in &report.conflicts {
+        eprintln!("Warning: Address conflict: {} is claimed by {} MACs", conflict.ip, conflict.macs.len());
+        for sighting in &conflict.macs {
+            eprintln!("    {}\tfirst seen +{:.3}s\tlast seen +{:.3}s\t{} frame{}", mac_string(sighting.mac),
+                since_start(sighting.first_seen), since_start(sighting.last_seen), sighting.frames,
+                if sighting.frames == 1 { "" } else { "s" });
         }
     }
+    !report.conflicts.is_empty()
+}
 
-    fn ensure_host_entry(mac: MacAddr) -> Result<()> {
-        let mac_str = mac.to_string().to_uppercase();
-        
-        // Read existing entries
-        let mut entries = Vec::new();
-        if Path::new("labels.txt").exists() {
-            let file = File::open("labels.txt")?;
-            for line in io::BufReader::new(file).lines() {
-                entries.push(line?);
-            }
-        }
-
-        // Check if MAC already exists
-        if !entries.iter().any(|line| line.starts_with(&mac_str)) {
-            // Add new entry with blank label and hostname
-            entries.push(format!("{}==", mac_str));
-            
-            // Write back all entries
-            let mut file = std::fs::OpenOptions::new()
-                .write(true)
-                .truncate(true)
-                .open("labels.txt")?;
-            
-            for entry in entries {
-                writeln!(file, "{}", entry)?;
-            }
+/// Warns about MACs that answered for more than `threshold` addresses, telling
+/// proxy ARP on the gateway apart from other devices. The gateway is `gateway`
+/// if given, or else the default route of the report's interface.
+fn print_shared_macs(report: &ScanReport, threshold: usize, gateway: Option<Ipv4Addr>) {
+    let gateway = gateway.or_else(|| report.interface.as_deref().and_then(default_gateway));
+    for shared in shared_macs(report, threshold, gateway) {
+        let mut ips: Vec<String> = shared.ips.iter().take(8).map(|ip| ip.to_string()).collect();
+        if shared.ips.len() > 8 {
+            ips.push(format!("and {} more", shared.ips.len() - 8));
         }
-
-        Ok(())
+        let device = match &shared.label {
+            Some(label) => format!("{} ({})", mac_string(shared.mac), label),
+            None => mac_string(shared.mac),
+        };
+        let verdict = if shared.is_gateway {
+            "the gateway, likely proxy ARP"
+        } else if gateway.is_some() {
+            "not the gateway, possible ARP spoofing"
+        } else {
+            "gateway unknown, proxy ARP or ARP spoofing (use --gateway)"
+        };
+        eprintln!("Warning: {} answers for {} addresses, {}: {}", device, shared.ips.len(), verdict, ips.join(", "));
     }
+}
 
-    fn update_hosts_file(&self) -> Result<()> {
-        // Check if running on Windows
-        if !cfg!(windows) {
-            return Err(Box::new(io::Error::new(
-                io::ErrorKind::Unsupported,
-                "The --add-hosts option is only supported on Windows"
-            )));
-        }
+/// Warns about ARP frames that failed validation and were left out of the results.
+fn print_anomalies(report: &ScanReport) {
+    if report.anomalies.is_empty() {
+        return;
+    }
+    eprintln!("Warning: Ignored suspicious ARP frames:");
+    for anomaly in &report.anomalies {
+        eprintln!("    {}", anomaly);
+    }
+}
 
-        let hosts_path = Path::new("C:\\Windows\\System32\\drivers\\etc\\hosts");
-        if !hosts_path.exists() {
-            return Err(Box::new(io::Error::new(
-                io::ErrorKind::NotFound,
-                "Windows hosts file not found. Please ensure you're running on Windows."
-            )));
-        }
+/// Exits with [`CONFLICT_EXIT_CODE`] if a conflict was found and the caller asked
+/// for it, once all output is printed.
+fn finish(conflicted: bool, fail_on_conflict: bool) -> Result<()> {
+    if conflicted && fail_on_conflict {
+        process::exit(CONFLICT_EXIT_CODE);
+    }
+    Ok(())
+}
 
-        // Read existing entries
-        let file_content = std::fs::read_to_string(hosts_path)?;
-        let mut entries: Vec<(Ipv4Addr, String)> = Vec::new();
-        let mut existing_ips = std::collections::HashSet::new();
-        let mut existing_hostnames = std::collections::HashSet::new();
-        
-        for line in file_content.lines() {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 2 {
-                if let Ok(ip) = parts[0].parse::<Ipv4Addr>() {
-                    existing_ips.insert(ip);
-                    existing_hostnames.insert(parts[1]);
-                }
-            }
-        }
+fn interface_flags(interface: &InterfaceInfo) -> Vec<&'static str> {
+    let mut flags = Vec::new();
+    if interface.is_up {
+        flags.push("up");
+    }
+    if interface.is_loopback {
+        flags.push("loopback");
+    }
+    if interface.is_point_to_point {
+        flags.push("point-to-point");
+    }
+    flags
+}
 
-        // Prepare new entries and updates
-        let mut new_entries = String::new();
-        let hosts = self.discovered_hosts.lock().unwrap();
-        
-        // First, collect all IPs and hostnames from mappings.txt that we'll be managing
-        let mut managed_ips = std::collections::HashSet::new();
-        let mut managed_hostnames = std::collections::HashSet::new();
-        
-        if let Some(labels) = &self.labels {
-            for (mac, (_, hostname)) in labels.iter() {
-                if let Some(hostname) = hostname {
-                    managed_hostnames.insert(hostname.clone());
-                }
-                if let Some((ip, _)) = hosts.iter().find(|(_, m)| m.to_string().to_uppercase() == *mac) {
-                    managed_ips.insert(*ip);
-                }
-            }
+fn print_interfaces(interfaces: &[InterfaceInfo]) {
+    for interface in interfaces {
+        let flags = interface_flags(interface);
+        println!("{} (index {})", interface.name, interface.index);
+        println!("    MAC:      {}", interface.mac.map(mac_string).unwrap_or_else(|| "-".to_string()));
+        for network in &interface.ipv4 {
+            println!("    IPv4:     {}", network);
         }
-
-        // Remove all existing entries that match our managed IPs or hostnames
-        let mut lines: Vec<&str> = file_content.lines().collect();
-        lines.retain(|line| {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 2 {
-                // Keep the line if it's not an IP entry
-                if parts[0].parse::<Ipv4Addr>().is_err() {
-                    true
-                } else {
-                    // Keep the line if its IP and hostname are not in our managed sets
-                    if let Ok(ip) = parts[0].parse::<Ipv4Addr>() {
-                        !managed_ips.contains(&ip) && !managed_hostnames.contains(parts[1])
-                    } else {
-                        true
-                    }
-                }
-            } else {
-                true
-            }
-        });
-        let file_content = lines.join("\n");
-
-        // Now prepare new entries
-        if let Some(labels) = &self.labels {
-            // Create a vector of entries to sort
-            let mut entries: Vec<(Ipv4Addr, String)> = Vec::new();
-            for (ip, mac) in hosts.iter() {
-                let mac_str = mac.to_string().to_uppercase();
-                if let Some((_, Some(hostname))) = labels.get(&mac_str) {
-                    entries.push((*ip, hostname.clone()));
-                }
-            }
-            // Sort entries by IP address
-            entries.sort_by_key(|&(ip, _)| ip.octets());
-            
-            // Calculate the maximum IP width from both existing and new entries
-            let max_ip_width = file_content.lines()
-                .filter_map(|line| {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    if parts.len() >= 2 && parts[0].parse::<Ipv4Addr>().is_ok() {
-                        Some(parts[0].len())
-                    } else {
-                        None
-                    }
-                })
-                .chain(entries.iter().map(|(ip, _)| ip.to_string().len()))
-                .max()
-                .unwrap_or(15);
-            
-            // Create the new entries string from sorted entries
-            for (ip, hostname) in entries {
-                new_entries.push_str(&format!("{:<width$}\t\t{}\n", ip, hostname, width = max_ip_width));
-            }
-
-            if !new_entries.is_empty() {
-                if self.options.dummy_mode {
-                    println!("\nEntries to be added:");
-                    println!("----------------------------------------");
-                    print!("{}", new_entries);
-                    println!("----------------------------------------");
-                } else {
-                    // Write back the file with updates
-                    let mut file = std::fs::OpenOptions::new()
-                        .write(true)
-                        .truncate(true)
-                        .open(hosts_path)?;
-                    
-                    // Normalize existing entries to use two tabs and align IPs
-                    let normalized_content = file_content.lines()
-                        .map(|line| {
-                            let parts: Vec<&str> = line.split_whitespace().collect();
-                            if parts.len() >= 2 && parts[0].parse::<Ipv4Addr>().is_ok() {
-                                format!("{:<width$}\t\t{}", parts[0], parts[1], width = max_ip_width)
-                            } else {
-                                line.to_string()
-                            }
-                        })
-                        .collect::<Vec<_>>()
-                        .join("\n");
-
-                    // Write the existing content first
-                    std::io::Write::write_all(&mut file, normalized_content.as_bytes())?;
-                    if !normalized_content.is_empty() && !normalized_content.ends_with('\n') {
-                        std::io::Write::write_all(&mut file, b"\n")?;
-                    }
-                    
-                    // Then add the new entries
-                    std::io::Write::write_all(&mut file, new_entries.as_bytes())?;
-                    
-                    if self.options.verbose {
-                        let new_count = new_entries.lines().count();
-                        println!("Updated hosts file with {} entries", new_count);
-                    }
-                }
-            } else if self.options.dummy_mode {
-                println!("\nNo changes would be made to hosts file.");
-            }
+        for network in &interface.ipv6 {
+            println!("    IPv6:     {}", network);
         }
-
-        Ok(())
+        println!("    Flags:    {}", if flags.is_empty() { "-".to_string() } else { flags.join(", ") });
+        println!("    ARP scan: {}", if interface.arp_capable { "yes" } else { "no" });
     }
+}
 
-    fn scan_network(&self) -> Result<()> {
-        let (mut tx, rx) = self.create_channel()?;
-        let listening_thread = self.start_listener(rx);
-
-        if let IpAddr::V4(local_ip) = self.local_ip {
-            // Add local machine to discovered hosts
-            if let Some(local_mac) = self.interface.mac {
-                let mut hosts = self.discovered_hosts.lock().unwrap();
-                hosts.insert(local_ip, local_mac);
-                if self.options.verbose {
-                    println!("Local machine: {} (MAC: {})", local_ip, local_mac.to_string().to_uppercase());
-                }
-            }
-
-            let network = if let Some(custom_range) = &self.options.custom_range {
-                if self.options.verbose {
-                    println!("Using custom network range: {}", custom_range);
-                }
-                custom_range.clone()
-            } else {
-                if let Some(network) = self.interface
-                    .ips
-                    .iter()
-                    .find(|ip| ip.ip() == self.local_ip)
-                {
-                    if self.options.verbose {
-                        println!("Auto-detected network: {}", network);
-                    }
-                    network.clone()
-                } else {
-                    return Err("Failed to find network".into());
-                }
-            };
-
-            if let IpNetwork::V4(network) = network {
-                if self.options.verbose {
-                    println!("Sending ARP requests...");
-                }
-
-                let mut packets: Vec<_> = network.iter()
-                    .map(|ip| self.create_arp_request(ip))
-                    .collect::<Result<Vec<_>>>()?;
-
-                for chunk in packets.chunks_mut(32) {
-                    for packet in chunk {
-                        tx.send_to(packet, None);
-                    }
-                    thread::sleep(Duration::from_micros(100));
-                }
-            } else {
-                return Err("Only IPv4 networks are supported".into());
-            }
+/// Quotes `value` as a JSON string.
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
         }
+    }
+    quoted.push('"');
+    quoted
+}
 
-        listening_thread.join().unwrap();
-        self.print_results();
-        
-        if self.options.update_hosts {
-            self.update_hosts_file()?;
-        }
-        
-        Ok(())
-    }
-
-    fn print_results(&self) {
-        let hosts = self.discovered_hosts.lock().unwrap();
-        let mut hosts: Vec<_> = hosts.iter().collect();
-        hosts.sort_by_key(|&(ip, _)| ip.octets());
-        
-        // Calculate maximum widths for each column
-        let mut max_ip_width = 15;  // Minimum width for IP
-        let mut max_mac_width = 17;  // Minimum width for MAC
-        let mut max_label_width = 0;
-        let mut max_hostname_width = 0;
-
-        // First pass: calculate maximum widths
-        for (ip, mac) in &hosts {
-            let mac_str = mac.to_string().to_uppercase();
-            max_ip_width = max_ip_width.max(ip.to_string().len());
-            max_mac_width = max_mac_width.max(mac_str.len());
-            
-            if let Some(labels) = &self.labels {
-                if let Some((label, hostname)) = labels.get(&mac_str) {
-                    max_label_width = max_label_width.max(label.len());
-                    if let Some(hostname) = hostname {
-                        max_hostname_width = max_hostname_width.max(hostname.len());
-                    }
-                }
-            }
-        }
+fn json_array<T: ToString>(values: &[T]) -> String {
+    let items: Vec<String> = values.iter().map(|value| json_string(&value.to_string())).collect();
+    format!("[{}]", items.join(", "))
+}
 
-        // Print data rows with proper alignment
-        for (ip, mac) in hosts {
-            let mac_str = mac.to_string().to_uppercase();
-            if let Some(labels) = &self.labels {
-                if let Some((label, hostname)) = labels.get(&mac_str) {
-                    match hostname {
-                        Some(hostname) => println!("{:<ip_width$}\t{:<mac_width$}\t{:<hostname_width$}\t{:<label_width$}",
-                            ip, mac_str, hostname, label,
-                            ip_width = max_ip_width,
-                            mac_width = max_mac_width,
-                            hostname_width = max_hostname_width,
-                            label_width = max_label_width),
-                        None => println!("{:<ip_width$}\t{:<mac_width$}\t{:<label_width$}",
-                            ip, mac_str, label,
-                            ip_width = max_ip_width,
-                            mac_width = max_mac_width,
-                            label_width = max_label_width),
-                    }
-                    continue;
-                }
-            }
-            // If no label or labels not enabled, print without label
-            println!("{:<ip_width$}\t{:<mac_width$}",
-                ip, mac_str,
-                ip_width = max_ip_width,
-                mac_width = max_mac_width);
-        }
+fn print_interfaces_json(interfaces: &[InterfaceInfo]) {
+    println!("[");
+    for (i, interface) in interfaces.iter().enumerate() {
+        let mac = interface.mac.map(mac_string).map_or("null".to_string(), |mac| json_string(&mac));
+        println!("  {{");
+        println!("    \"name\": {},", json_string(&interface.name));
+        println!("    \"index\": {},", interface.index);
+        println!("    \"mac\": {},", mac);
+        println!("    \"ipv4\": {},", json_array(&interface.ipv4));
+        println!("    \"ipv6\": {},", json_array(&interface.ipv6));
+        println!("    \"up\": {},", interface.is_up);
+        println!("    \"loopback\": {},", interface.is_loopback);
+        println!("    \"point_to_point\": {},", interface.is_point_to_point);
+        println!("    \"arp_capable\": {}", interface.arp_capable);
+        println!("  }}{}", if i + 1 < interfaces.len() { "," } else { "" });
     }
+    println!("]");
 }
 
 fn print_usage() {
     println!("arp-scan - Fast ARP network scanner\n");
     println!("Usage:");
-    println!("  arp-scan [OPTIONS]\n");
+    println!("  arp-scan [OPTIONS]");
+    println!("  arp-scan interfaces [--json]\n");
     println!("Description:");
-    println!("  Scans the local network using ARP requests to discover active hosts.\n");
+    println!("  Scans the local network using ARP requests to discover active hosts.");
+    println!("  The interfaces command lists network interfaces and whether they can be scanned.\n");
     println!("Options:");
     println!("  -v, --verbose     Print detailed progress information");
     println!("  -f, --fast        Use shorter timeouts for quick-responding networks");
-    println!("  -r, --range <IP>  Scan custom IP range (e.g., 192.168.0.0/24)");
+    println!("  -r, --range <IP>  Scan custom targets: CIDRs, addresses, dash or octet ranges,");
+    println!("                    comma-separated and repeatable (e.g., 192.168.0.0/24,10.0.0.1)");
+    println!("  --file <FILE>     Scan the targets listed in FILE, one or more per line");
+    println!("  --exclude <IP>    Never probe these targets (same forms as -r, repeatable)");
+    println!("  --exclude-file <FILE>  Never probe the targets listed in FILE");
+    println!("  --strict          Refuse targets outside the interface's networks instead of warning");
+    println!("  --include-all     Also probe network, broadcast and own addresses");
+    println!("  -6, --ipv6        Also discover IPv6 neighbours and list their addresses");
+    println!("  --latency         Time each reply and show min/avg/max response times in ms");
+    println!("  --watch <SECS>    Rescan every SECS seconds and print hosts that appeared,");
+    println!("                    disappeared or changed MAC or IP address");
+    println!("  --passive         Send nothing; learn hosts from ARP traffic seen on the network");
+    println!("  --duration <SECS>  Stop passive listening after SECS seconds and print the table");
+    println!("  --baseline <FILE>  With --passive, alert on ARP traffic that contradicts the");
+    println!("                    IP and MAC pairs in FILE (saved scan output)");
+    println!("  --baseline-labels  With --passive, trust the IPs given in labels.txt");
+    println!("  --max-ips-per-mac <N>  Warn about MACs answering for more than N addresses (default: 4)");
+    println!("  --gateway <IP>    Gateway address for telling proxy ARP from spoofing (default: from routes)");
+    println!("  --fail-on-conflict  Exit with status 2 if several MACs claimed the same IP");
+    println!("  -I, --interface <NAME>  Scan on this interface instead of the primary one");
+    println!("  --all-interfaces  Scan every IPv4 network on every usable interface in parallel");
     println!("  -l, --lookup      Look up labels from labels.txt file");
     println!("  --add-hosts       Update Windows hosts file with discovered hostnames");
     println!("  --dummy          Preview hosts file updates without making changes");
+    println!("  --read <FILE>     Read ARP traffic from a pcap/pcapng file instead of scanning");
+    println!("  --include-requests  With --read, also learn hosts from ARP requests they sent");
+    println!("  --write-pcap <FILE>  Save every frame sent and received to a pcap file");
+    println!("  --retries <N>     Re-send to unanswered targets up to N more times");
+    println!("  --backoff <MS>    Wait before the first retry, doubled each round (default: 250)");
+    println!("  --rate <PPS>      Send at most PPS requests per second");
+    println!("  --bandwidth <BPS>  Keep requests under BPS bits/s on the wire (k, M, G suffixes allowed)");
+    println!("  --random          Send to targets in a random order");
+    println!("  --seed <N>        Seed for a repeatable random order (implies --random)");
+    println!("  --idle <MS>       Stop once no new host answered for this long (default: 500, fast: 100)");
+    println!("  --max-wait <MS>   Never listen longer than this after sending (default: 5000, fast: 1000)");
     println!("  -h, --help        Display this help message\n");
     println!("Output Format:");
     println!("  Default:");
@@ -534,36 +281,234 @@ fn print_usage() {
     println!("  arp-scan -v                       Perform a scan with detailed progress information");
     println!("  arp-scan -f                       Perform a faster scan with shorter timeouts");
     println!("  arp-scan -r 192.168.1.0/24       Scan a specific network range");
+    println!("  arp-scan -r 10.0.0.10-10.0.0.50  Scan a range of addresses");
+    println!("  arp-scan -r 10.0.1-3.0-255       Scan 10.0.1.0 to 10.0.3.255 by octet ranges");
+    println!("  arp-scan --file targets.txt      Scan the targets listed in a file");
+    println!("  arp-scan --exclude 192.168.1.50-192.168.1.59  Scan without probing a fragile range");
+    println!("  arp-scan -I eth1                  Scan the network attached to eth1");
+    println!("  arp-scan interfaces               List interfaces usable with -I");
+    println!("  arp-scan --all-interfaces         Scan all attached networks, showing the interface column");
+    println!("  arp-scan interfaces --json        List interfaces as JSON for scripts");
     println!("  arp-scan -l                       Include labels from labels.txt");
     println!("  arp-scan -l --add-hosts          Update hosts file with discovered hostnames");
-    println!("  arp-scan -l --add-hosts --dummy  Preview hosts file updates\n");
+    println!("  arp-scan -l --add-hosts --dummy  Preview hosts file updates");
+    println!("  arp-scan --read capture.pcap     List hosts seen in a saved capture");
+    println!("  arp-scan --write-pcap scan.pcap  Scan and keep the traffic for Wireshark");
+    println!("  arp-scan --retries 2             Retry unanswered targets on lossy links");
+    println!("  arp-scan --rate 500              Limit sending to 500 packets per second");
+    println!("  arp-scan --bandwidth 1M          Limit sending to 1 Mbit/s");
+    println!("  arp-scan --random --seed 42      Probe targets in a repeatable random order");
+    println!("  arp-scan -6                      Show IPv6 addresses next to each host");
+    println!("  arp-scan --latency --retries 2   Show how quickly each host answers");
+    println!("  arp-scan --watch 60               Report changes on the network every minute");
+    println!("  arp-scan --passive --duration 300  Listen for five minutes without sending");
+    println!("  arp-scan --passive --baseline known.txt  Watch for ARP spoofing against a saved scan\n");
     println!("Label File Format (labels.txt):");
     println!("  MAC_ADDRESS=LABEL=HOSTNAME");
     println!("  Example: 40:0D:10:88:92:90=Router=router.local");
-    println!("  Note: HOSTNAME is optional\n");
+    println!("  Note: HOSTNAME is optional; MAC=LABEL=HOSTNAME=IP adds a trusted IP for --baseline-labels\n");
     println!("Notes:");
     println!("  - Requires administrator/root privileges");
-    println!("  - Automatically detects and uses the primary network interface");
+    println!("  - Automatically detects and uses the primary network interface unless -I is given");
     println!("  - MAC addresses are displayed in uppercase");
     println!("  - Fast mode (-f) reduces scan time but may miss slower hosts");
-    println!("  - Custom range option overrides auto-detected network range");
+    println!("  - The scan ends as soon as every target answered or replies stop arriving");
+    println!("  - Hosts are also learned from the ARP requests and gratuitous ARPs they send");
+    println!("  - Custom targets override the auto-detected network range");
+    println!("  - Overlapping targets are merged, so each address is probed once per round");
+    println!("  - Excluded addresses are removed before any request is built");
+    println!("  - Network, broadcast and own addresses are skipped (except on /31 networks)");
+    println!("  - IPv6 hosts without an IPv4 address are listed after the others");
+    println!("  - With --latency, every retry round probes all targets again for another sample");
+    println!("  - --watch runs until interrupted; use --retries to avoid false disappearances");
+    println!("  - --passive without --duration prints each host as it is seen until interrupted");
+    println!("  - ARP watch alerts: mac-changed, gateway-claimed and unsolicited-reply");
+    println!("  - Replies not addressed to us, from unprobed addresses, or whose Ethernet source");
+    println!("    differs from the ARP sender are ignored and reported on stderr");
+    println!("  - Addresses claimed by more than one MAC are reported on stderr as conflicts");
+    println!("  - The gateway is read from /proc/net/route on Linux; pass --gateway elsewhere");
+    println!("  - ARP only reaches the local segment; targets behind a router never answer");
+    println!("  - --all-interfaces cannot be combined with -r, --file or -I, and writes one");
+    println!("    pcap file per interface (scan.pcap becomes scan-eth0.pcap, ...)");
     println!("  - Labels file (labels.txt) is optional");
     println!("  - --add-hosts option requires --lookup and hostnames in labels.txt");
     println!("  - --dummy option can be used with --add-hosts to preview changes");
+    println!("  - --read needs no privileges; only Ethernet captures are supported");
+}
+
+/// Parses a bit rate such as `250000`, `500k`, `1.5M` or `1G`.
+fn parse_bandwidth(value: &str) -> std::result::Result<u64, String> {
+    let (number, multiplier) = match value.chars().last() {
+        Some('k' | 'K') => (&value[..value.len() - 1], 1e3),
+        Some('m' | 'M') => (&value[..value.len() - 1], 1e6),
+        Some('g' | 'G') => (&value[..value.len() - 1], 1e9),
+        _ => (value, 1.0),
+    };
+    let bits = number.parse::<f64>().map_err(|e| e.to_string())? * multiplier;
+    if !bits.is_finite() || bits < 1.0 {
+        return Err("must be at least 1 bit/s".to_string());
+    }
+    Ok(bits as u64)
+}
+
+/// Returns the value following the first occurrence of any of `names`.
+fn option_value<'a>(args: &'a [String], names: &[&str]) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| names.contains(&arg.as_str()))
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+/// Returns the values following every occurrence of any of `names`.
+fn option_values<'a>(args: &'a [String], names: &[&str]) -> Vec<&'a str> {
+    args.windows(2)
+        .filter(|pair| names.contains(&pair[0].as_str()))
+        .map(|pair| pair[1].as_str())
+        .collect()
 }
 
 fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
-    
-    // Parse custom range if provided
-    let custom_range = args.iter()
-        .position(|arg| arg == "-r" || arg == "--range")
-        .and_then(|i| args.get(i + 1))
-        .map(|range| IpNetwork::from_str(range))
+
+    if args.get(1).is_some_and(|arg| arg == "interfaces") {
+        let interfaces = list_interfaces();
+        if args.iter().any(|arg| arg == "--json") {
+            print_interfaces_json(&interfaces);
+        } else {
+            print_interfaces(&interfaces);
+        }
+        return Ok(());
+    }
+
+    // Parse custom targets if provided, merging every -r and --file into one set
+    let target_specs = option_values(&args, &["-r", "--range"]);
+    let target_files = option_values(&args, &["--file"]);
+    let targets = if target_specs.is_empty() && target_files.is_empty() {
+        None
+    } else {
+        let mut targets = TargetSet::new();
+        for spec in target_specs {
+            let parsed = TargetSet::from_str(spec).map_err(|e| format!("Invalid IP range: {}", e))?;
+            targets = targets.union(&parsed);
+        }
+        for path in target_files {
+            targets = targets.union(&TargetSet::read_file(path)?);
+        }
+        if targets.is_empty() {
+            return Err("No targets to scan".into());
+        }
+        Some(targets)
+    };
+
+    let exclude_specs = option_values(&args, &["--exclude"]);
+    let exclude_files = option_values(&args, &["--exclude-file"]);
+    let exclude = if exclude_specs.is_empty() && exclude_files.is_empty() {
+        None
+    } else {
+        let mut exclude = TargetSet::new();
+        for spec in exclude_specs {
+            let parsed = TargetSet::from_str(spec).map_err(|e| format!("Invalid exclusion: {}", e))?;
+            exclude = exclude.union(&parsed);
+        }
+        for path in exclude_files {
+            exclude = exclude.union(&TargetSet::read_file(path)?);
+        }
+        Some(exclude)
+    };
+
+    let retries = option_value(&args, &["--retries"])
+        .map(u32::from_str)
+        .transpose()
+        .map_err(|e| format!("Invalid retry count: {}", e))?
+        .unwrap_or(0);
+    let retry_backoff = option_value(&args, &["--backoff"])
+        .map(u64::from_str)
+        .transpose()
+        .map_err(|e| format!("Invalid backoff: {}", e))?
+        .map(Duration::from_millis);
+    let idle_timeout = option_value(&args, &["--idle"])
+        .map(u64::from_str)
+        .transpose()
+        .map_err(|e| format!("Invalid idle timeout: {}", e))?
+        .map(Duration::from_millis);
+    let max_wait = option_value(&args, &["--max-wait"])
+        .map(u64::from_str)
+        .transpose()
+        .map_err(|e| format!("Invalid maximum wait: {}", e))?
+        .map(Duration::from_millis);
+
+    let rate = option_value(&args, &["--rate"])
+        .map(u32::from_str)
+        .transpose()
+        .map_err(|e| format!("Invalid rate: {}", e))?;
+    if rate == Some(0) {
+        return Err("Invalid rate: must be at least 1 packet per second".into());
+    }
+    let bandwidth = option_value(&args, &["--bandwidth"])
+        .map(parse_bandwidth)
+        .transpose()
+        .map_err(|e| format!("Invalid bandwidth: {}", e))?;
+
+    let watch_interval = option_value(&args, &["--watch"])
+        .map(u64::from_str)
         .transpose()
-        .map_err(|e| format!("Invalid IP range: {}", e))?;
+        .map_err(|e| format!("Invalid watch interval: {}", e))?;
+    if watch_interval == Some(0) {
+        return Err("Invalid watch interval: must be at least 1 second".into());
+    }
+    let watch_interval = watch_interval.map(Duration::from_secs);
+
+    let passive = args.iter().any(|arg| arg == "--passive");
+    let passive_duration = option_value(&args, &["--duration"])
+        .map(u64::from_str)
+        .transpose()
+        .map_err(|e| format!("Invalid duration: {}", e))?
+        .map(Duration::from_secs);
+    if passive_duration.is_some() && !passive {
+        eprintln!("Error: --duration requires --passive");
+        return Err("Invalid options".into());
+    }
+
+    // A trusted baseline turns passive listening into an ARP watch
+    let baseline_file = option_value(&args, &["--baseline"]);
+    let baseline_labels = args.iter().any(|arg| arg == "--baseline-labels");
+    let baseline = if baseline_file.is_none() && !baseline_labels {
+        None
+    } else {
+        let mut baseline = Baseline::new();
+        if baseline_labels {
+            baseline.extend(Baseline::from_labels()?);
+        }
+        if let Some(path) = baseline_file {
+            baseline.extend(Baseline::read_file(path)?);
+        }
+        if baseline.is_empty() {
+            eprintln!("Warning: The baseline is empty; only gateway claims and unsolicited replies are reported");
+        }
+        Some(baseline)
+    };
+    if baseline.is_some() && !passive {
+        eprintln!("Error: --baseline and --baseline-labels require --passive");
+        return Err("Invalid options".into());
+    }
+
+    let seed = option_value(&args, &["--seed"])
+        .map(u64::from_str)
+        .transpose()
+        .map_err(|e| format!("Invalid seed: {}", e))?;
 
     let update_hosts = args.iter().any(|arg| arg == "--add-hosts");
+    let fail_on_conflict = args.iter().any(|arg| arg == "--fail-on-conflict");
+    let gateway = option_value(&args, &["--gateway"])
+        .map(Ipv4Addr::from_str)
+        .transpose()
+        .map_err(|e| format!("Invalid gateway: {}", e))?;
+    let max_ips_per_mac = option_value(&args, &["--max-ips-per-mac"])
+        .map(usize::from_str)
+        .transpose()
+        .map_err(|e| format!("Invalid maximum addresses per MAC: {}", e))?
+        .unwrap_or(DEFAULT_SHARED_MAC_THRESHOLD);
+    let print_analysis = |report: &ScanReport| print_shared_macs(report, max_ips_per_mac, gateway);
     let lookup_labels = args.iter().any(|arg| arg == "-l" || arg == "--lookup");
     let dummy_mode = args.iter().any(|arg| arg == "--dummy");
 
@@ -573,20 +518,178 @@ fn main() -> Result<()> {
         return Err("Invalid options".into());
     }
 
-    let options = ScanOptions {
-        verbose: args.iter().any(|arg| arg == "-v" || arg == "--verbose"),
-        fast_mode: args.iter().any(|arg| arg == "-f" || arg == "--fast"),
-        custom_range,
-        lookup_labels,
-        update_hosts,
-        dummy_mode,
-    };
+    let read_path = option_value(&args, &["--read"]);
+    if read_path.is_some() && update_hosts {
+        eprintln!("Error: --add-hosts cannot be used with --read");
+        return Err("Invalid options".into());
+    }
+    let include_requests = args.iter().any(|arg| arg == "--include-requests");
+    if include_requests && read_path.is_none() {
+        eprintln!("Error: --include-requests only applies to --read; live scans always learn from requests");
+        return Err("Invalid options".into());
+    }
+
+    let interface = option_value(&args, &["-I", "--interface"]);
+    let ipv6 = args.iter().any(|arg| arg == "-6" || arg == "--ipv6");
+    let latency = args.iter().any(|arg| arg == "--latency");
+    if latency && (read_path.is_some() || passive) {
+        eprintln!("Error: --latency cannot be used with --read or --passive");
+        return Err("Invalid options".into());
+    }
+    let all_interfaces = args.iter().any(|arg| arg == "--all-interfaces");
+    if watch_interval.is_some() && (read_path.is_some() || all_interfaces) {
+        eprintln!("Error: --watch cannot be used with --read or --all-interfaces");
+        return Err("Invalid options".into());
+    }
+    if passive && (watch_interval.is_some() || read_path.is_some() || all_interfaces) {
+        eprintln!("Error: --passive cannot be used with --watch, --read or --all-interfaces");
+        return Err("Invalid options".into());
+    }
+    if all_interfaces && (targets.is_some() || interface.is_some()) {
+        eprintln!("Error: --all-interfaces cannot be used with --range, --file or --interface");
+        return Err("Invalid options".into());
+    }
+
+    let mut builder = ScanOptions::builder();
+    if let Some(backoff) = retry_backoff {
+        builder = builder.retry_backoff(backoff);
+    }
+    if let Some(idle_timeout) = idle_timeout {
+        builder = builder.idle_timeout(idle_timeout);
+    }
+    if let Some(max_wait) = max_wait {
+        builder = builder.max_wait(max_wait);
+    }
+    let options = builder
+        .verbose(args.iter().any(|arg| arg == "-v" || arg == "--verbose"))
+        .fast_mode(args.iter().any(|arg| arg == "-f" || arg == "--fast"))
+        .targets(targets)
+        .exclude(exclude)
+        .strict(args.iter().any(|arg| arg == "--strict"))
+        .include_all(args.iter().any(|arg| arg == "--include-all"))
+        .ipv6(ipv6)
+        .measure_latency(latency)
+        .lookup_labels(lookup_labels)
+        .dummy_mode(dummy_mode)
+        .include_requests(include_requests)
+        .write_pcap(option_value(&args, &["--write-pcap"]).map(PathBuf::from))
+        .retries(retries)
+        .rate(rate)
+        .bandwidth(bandwidth)
+        .random(args.iter().any(|arg| arg == "--random") || seed.is_some())
+        .seed(seed)
+        .interface(interface.map(String::from))
+        .build();
 
     if args.iter().any(|arg| arg == "-h" || arg == "--help") {
         print_usage();
         return Ok(());
     }
 
+    if let Some(path) = read_path {
+        let report = read_capture(path, &options)?;
+        print_results(&report.hosts, false, ipv6, latency);
+        print_ipv6_only(&report.ipv6_only);
+        print_analysis(&report);
+        print_anomalies(&report);
+        let conflicted = print_conflicts(&report);
+        return finish(conflicted, fail_on_conflict);
+    }
+
+    if all_interfaces {
+        let scanners = ArpScanner::all_interfaces(options)?;
+        let reports = ArpScanner::scan_all(&scanners)?;
+        let mut hosts: Vec<HostEntry> = reports.iter().flat_map(|report| report.hosts.iter().cloned()).collect();
+        hosts.sort_by(|a, b| a.ip.cmp(&b.ip).then_with(|| a.interface.cmp(&b.interface)));
+        print_results(&hosts, true, ipv6, latency);
+        let mut neighbors: Vec<NeighborEntry> = reports.iter().flat_map(|report| report.ipv6_only.iter().cloned()).collect();
+        neighbors.sort_by_key(|neighbor| neighbor.ip);
+        print_ipv6_only(&neighbors);
+        let mut conflicted = false;
+        for report in &reports {
+            print_analysis(report);
+            print_anomalies(report);
+            conflicted |= print_conflicts(report);
+        }
+
+        if update_hosts {
+            for (scanner, report) in scanners.iter().zip(&reports) {
+                scanner.update_hosts_file(report)?;
+            }
+        }
+        return finish(conflicted, fail_on_conflict);
+    }
+
     let scanner = ArpScanner::new(options)?;
-    scanner.scan_network()
+
+    if let Some(interval) = watch_interval {
+        // Print the first scan in full, then only what changed
+        let mut first = true;
+        let mut update_error = None;
+        let mut conflicted = false;
+        scanner.watch(interval, |report, events| {
+            if first {
+                print_results(&report.hosts, false, ipv6, latency);
+                print_ipv6_only(&report.ipv6_only);
+            }
+            for event in events {
+                println!("{}", event);
+            }
+            print_analysis(report);
+            print_anomalies(report);
+            conflicted = print_conflicts(report);
+            if update_hosts && (first || !events.is_empty()) {
+                if let Err(e) = scanner.update_hosts_file(report) {
+                    update_error = Some(e);
+                    return false;
+                }
+            }
+            first = false;
+            // Stop at the first conflict when it should fail the run
+            !(conflicted && fail_on_conflict)
+        })?;
+        if let Some(e) = update_error {
+            return Err(e);
+        }
+        return finish(conflicted, fail_on_conflict);
+    }
+
+    if passive {
+        let report = match baseline {
+            Some(baseline) => {
+                // Alerts are printed as they come, the host table once --duration ends
+                let gateway = gateway.or_else(|| default_gateway(&scanner.interface().name));
+                let mut monitor = ArpMonitor::new(baseline, gateway);
+                scanner.arp_watch(&mut monitor, passive_duration, |alert| println!("{}", alert))?
+            }
+            None if passive_duration.is_none() => {
+                // Nothing is printed at the end of an endless listen, so show hosts as they come
+                scanner.passive(None, |host| print_results(std::slice::from_ref(host), false, false, false))?;
+                return Ok(());
+            }
+            None => scanner.passive(passive_duration, |_| {})?,
+        };
+        print_results(&report.hosts, false, ipv6, latency);
+        print_ipv6_only(&report.ipv6_only);
+        print_analysis(&report);
+        print_anomalies(&report);
+        let conflicted = print_conflicts(&report);
+        if update_hosts {
+            scanner.update_hosts_file(&report)?;
+        }
+        return finish(conflicted, fail_on_conflict);
+    }
+
+    let report = scanner.scan()?;
+    print_results(&report.hosts, false, ipv6, latency);
+    print_ipv6_only(&report.ipv6_only);
+    print_analysis(&report);
+    print_anomalies(&report);
+    let conflicted = print_conflicts(&report);
+
+    if update_hosts {
+        scanner.update_hosts_file(&report)?;
+    }
+
+    finish(conflicted, fail_on_conflict)
 }
@@ -0,0 +1,127 @@
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+use crate::collector::HostCollector;
+use crate::labels;
+use crate::options::ScanOptions;
+use crate::pcap::PcapReader;
+use crate::report::ScanReport;
+use crate::targets::TargetSet;
+use crate::Result;
+
+/// Builds a host table from the ARP traffic in a pcap or pcapng capture file.
+///
+/// Frames go through the same processing as a live scan, so replies (and
+/// requests and gratuitous announcements, with
+/// [`include_requests`](crate::ScanOptionsBuilder::include_requests)) produce
+/// the same report, labels included.
+pub fn read_capture<P: AsRef<Path>>(path: P, options: &ScanOptions) -> Result<ScanReport> {
+    let path = path.as_ref();
+    let mut reader = PcapReader::open(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if options.verbose {
+        println!("Reading ARP traffic from {}", path.display());
+    }
+
+    let labels = if options.lookup_labels {
+        Some(labels::load_labels()?)
+    } else {
+        None
+    };
+
+    let mut collector = HostCollector::new(options);
+    if !options.include_requests {
+        collector = collector.replies_only();
+    }
+    let mut first: Option<Duration> = None;
+    let mut last = Duration::ZERO;
+    let mut frame_count = 0usize;
+
+    while let Some(frame) = reader.next_frame()
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+    {
+        first.get_or_insert(frame.timestamp);
+        last = last.max(frame.timestamp);
+        frame_count += 1;
+        collector.process_packet(&frame.data, UNIX_EPOCH + frame.timestamp);
+    }
+
+    if options.verbose {
+        println!("Processed {} frames", frame_count);
+    }
+
+    let first = first.unwrap_or_default();
+    Ok(ScanReport {
+        interface: None,
+        local_ip: None,
+        targets: TargetSet::new(),
+        hosts: collector.host_entries(labels.as_ref(), None),
+        ipv6_only: collector.ipv6_only_entries(),
+        conflicts: collector.conflicts(),
+        anomalies: collector.anomalies(),
+        started: UNIX_EPOCH + first,
+        duration: last.saturating_sub(first),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::net::Ipv4Addr;
+    use pnet::packet::arp::ArpOperations;
+    use pnet::util::MacAddr;
+    use super::*;
+    use crate::frames::arp_frame;
+    use crate::pcap::PcapWriter;
+    use crate::report::HostSource;
+
+    fn mac(last: u8) -> MacAddr {
+        MacAddr::new(0x02, 0, 0, 0, 0x10, last)
+    }
+
+    #[test]
+    fn builds_host_table_from_capture() {
+        let gateway = Ipv4Addr::new(10, 1, 0, 1);
+        let frames = [
+            (10, arp_frame(ArpOperations::Request, mac(9), Ipv4Addr::new(10, 1, 0, 9), gateway)),
+            (11, arp_frame(ArpOperations::Reply, mac(1), gateway, Ipv4Addr::new(10, 1, 0, 9))),
+            (12, arp_frame(ArpOperations::Request, mac(7), Ipv4Addr::new(10, 1, 0, 7), Ipv4Addr::new(10, 1, 0, 7))),
+            (13, arp_frame(ArpOperations::Reply, mac(66), gateway, Ipv4Addr::new(10, 1, 0, 7))),
+            (14, arp_frame(ArpOperations::Reply, mac(1), gateway, Ipv4Addr::new(10, 1, 0, 7))),
+        ];
+
+        let path = std::env::temp_dir().join(format!("arp-scan-offline-{}.pcap", std::process::id()));
+        let mut writer = PcapWriter::create(&path).unwrap();
+        for (seconds, frame) in &frames {
+            writer.write_frame(UNIX_EPOCH + Duration::from_secs(*seconds), frame).unwrap();
+        }
+        writer.flush().unwrap();
+        drop(writer);
+
+        let report = read_capture(&path, &ScanOptions::builder().include_requests(true).build());
+        let replies_only = read_capture(&path, &ScanOptions::builder().build());
+        fs::remove_file(&path).unwrap();
+        let (report, replies_only) = (report.unwrap(), replies_only.unwrap());
+
+        let hosts: Vec<_> = report.hosts.iter().map(|host| (host.ip, host.mac, host.source)).collect();
+        assert_eq!(hosts, vec![
+            (gateway, mac(1), HostSource::Reply),
+            (Ipv4Addr::new(10, 1, 0, 7), mac(7), HostSource::Gratuitous),
+            (Ipv4Addr::new(10, 1, 0, 9), mac(9), HostSource::Request),
+        ]);
+        assert!(report.hosts.iter().all(|host| host.interface.is_none() && host.rounds.is_none()));
+
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].ip, gateway);
+        let macs: Vec<_> = report.conflicts[0].macs.iter().map(|sighting| (sighting.mac, sighting.frames)).collect();
+        assert_eq!(macs, vec![(mac(1), 2), (mac(66), 1)]);
+
+        assert!(report.anomalies.is_empty());
+        assert_eq!(report.started, UNIX_EPOCH + Duration::from_secs(10));
+        assert_eq!(report.duration, Duration::from_secs(4));
+
+        // Without requests only the gateway's replies count
+        let hosts: Vec<_> = replies_only.hosts.iter().map(|host| (host.ip, host.mac, host.source)).collect();
+        assert_eq!(hosts, vec![(gateway, mac(1), HostSource::Reply)]);
+        assert_eq!(replies_only.conflicts, report.conflicts);
+    }
+}
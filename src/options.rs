@@ -0,0 +1,306 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::targets::TargetSet;
+
+/// Settings that control how a scan is performed.
+///
+/// Use [`ScanOptions::builder`] to construct one; unless noted otherwise every
+/// setting defaults to off.
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    pub(crate) verbose: bool,
+    pub(crate) fast_mode: bool,
+    pub(crate) targets: Option<TargetSet>,
+    pub(crate) exclude: Option<TargetSet>,
+    pub(crate) strict: bool,
+    pub(crate) include_all: bool,
+    pub(crate) ipv6: bool,
+    pub(crate) measure_latency: bool,
+    pub(crate) lookup_labels: bool,
+    pub(crate) dummy_mode: bool,
+    pub(crate) include_requests: bool,
+    pub(crate) write_pcap: Option<PathBuf>,
+    pub(crate) retries: u32,
+    pub(crate) retry_backoff: Duration,
+    pub(crate) idle_timeout: Option<Duration>,
+    pub(crate) max_wait: Option<Duration>,
+    pub(crate) rate: Option<u32>,
+    pub(crate) bandwidth: Option<u64>,
+    pub(crate) random: bool,
+    pub(crate) seed: Option<u64>,
+    pub(crate) interface: Option<String>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            verbose: false,
+            fast_mode: false,
+            targets: None,
+            exclude: None,
+            strict: false,
+            include_all: false,
+            ipv6: false,
+            measure_latency: false,
+            lookup_labels: false,
+            dummy_mode: false,
+            include_requests: false,
+            write_pcap: None,
+            retries: 0,
+            retry_backoff: Duration::from_millis(250),
+            idle_timeout: None,
+            max_wait: None,
+            rate: None,
+            bandwidth: None,
+            random: false,
+            seed: None,
+            interface: None,
+        }
+    }
+}
+
+impl ScanOptions {
+    pub fn builder() -> ScanOptionsBuilder {
+        ScanOptionsBuilder::default()
+    }
+
+    pub fn verbose(&self) -> bool {
+        self.verbose
+    }
+
+    pub fn fast_mode(&self) -> bool {
+        self.fast_mode
+    }
+
+    /// Addresses to scan instead of the network attached to the local address, if given.
+    pub fn targets(&self) -> Option<&TargetSet> {
+        self.targets.as_ref()
+    }
+
+    /// Addresses that are never probed, if any.
+    pub fn exclude(&self) -> Option<&TargetSet> {
+        self.exclude.as_ref()
+    }
+
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn include_all(&self) -> bool {
+        self.include_all
+    }
+
+    pub fn ipv6(&self) -> bool {
+        self.ipv6
+    }
+
+    pub fn measure_latency(&self) -> bool {
+        self.measure_latency
+    }
+
+    pub fn lookup_labels(&self) -> bool {
+        self.lookup_labels
+    }
+
+    pub fn dummy_mode(&self) -> bool {
+        self.dummy_mode
+    }
+
+    pub fn include_requests(&self) -> bool {
+        self.include_requests
+    }
+
+    pub fn write_pcap(&self) -> Option<&Path> {
+        self.write_pcap.as_deref()
+    }
+
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    pub fn retry_backoff(&self) -> Duration {
+        self.retry_backoff
+    }
+
+    /// How long the listener waits for a new reply after the last request before
+    /// ending the scan: 500 ms, or 100 ms in fast mode, unless set explicitly.
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout.unwrap_or(Duration::from_millis(if self.fast_mode { 100 } else { 500 }))
+    }
+
+    /// Maximum requests sent per second, if limited.
+    pub fn rate(&self) -> Option<u32> {
+        self.rate
+    }
+
+    /// Maximum bits per second the requests may occupy on the wire, if limited.
+    pub fn bandwidth(&self) -> Option<u64> {
+        self.bandwidth
+    }
+
+    pub fn random(&self) -> bool {
+        self.random
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn interface(&self) -> Option<&str> {
+        self.interface.as_deref()
+    }
+
+    /// The longest the listener keeps going after the last request, however many
+    /// replies still arrive: 5 s, or 1 s in fast mode, unless set explicitly.
+    pub fn max_wait(&self) -> Duration {
+        self.max_wait.unwrap_or(Duration::from_millis(if self.fast_mode { 1000 } else { 5000 }))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptionsBuilder {
+    options: ScanOptions,
+}
+
+impl ScanOptionsBuilder {
+    /// Print detailed progress information while scanning.
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.options.verbose = verbose;
+        self
+    }
+
+    /// Use shorter timeouts for quick-responding networks.
+    pub fn fast_mode(mut self, fast_mode: bool) -> Self {
+        self.options.fast_mode = fast_mode;
+        self
+    }
+
+    /// Scan these addresses instead of the network attached to the local address.
+    pub fn targets(mut self, targets: Option<TargetSet>) -> Self {
+        self.options.targets = targets;
+        self
+    }
+
+    /// Never send to these addresses, whether they come from the custom targets or
+    /// the auto-detected network.
+    pub fn exclude(mut self, exclude: Option<TargetSet>) -> Self {
+        self.options.exclude = exclude;
+        self
+    }
+
+    /// Refuse to scan custom targets outside the interface's networks instead of
+    /// warning about them.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.options.strict = strict;
+        self
+    }
+
+    /// Also probe our own addresses and the network and broadcast addresses of the
+    /// interface's networks, which are skipped by default.
+    pub fn include_all(mut self, include_all: bool) -> Self {
+        self.options.include_all = include_all;
+        self
+    }
+
+    /// Also discover IPv6 neighbours with an all-nodes echo and Neighbor
+    /// Solicitations, and report their addresses next to the IPv4 hosts.
+    pub fn ipv6(mut self, ipv6: bool) -> Self {
+        self.options.ipv6 = ipv6;
+        self
+    }
+
+    /// Time every reply against the request it answers and report each host's
+    /// response times. Retry rounds then probe every target again, so each
+    /// round gives another sample.
+    pub fn measure_latency(mut self, measure_latency: bool) -> Self {
+        self.options.measure_latency = measure_latency;
+        self
+    }
+
+    /// Look up labels and hostnames for discovered MACs in `labels.txt`.
+    pub fn lookup_labels(mut self, lookup_labels: bool) -> Self {
+        self.options.lookup_labels = lookup_labels;
+        self
+    }
+
+    /// Preview hosts file updates without writing them.
+    pub fn dummy_mode(mut self, dummy_mode: bool) -> Self {
+        self.options.dummy_mode = dummy_mode;
+        self
+    }
+
+    /// Also learn hosts read from a capture from the ARP requests and gratuitous
+    /// announcements they sent, not only from replies. Live scans always do.
+    pub fn include_requests(mut self, include_requests: bool) -> Self {
+        self.options.include_requests = include_requests;
+        self
+    }
+
+    /// Record every frame sent and received during a live scan to this pcap file.
+    pub fn write_pcap(mut self, path: Option<PathBuf>) -> Self {
+        self.options.write_pcap = path;
+        self
+    }
+
+    /// Re-send to targets that have not answered, up to this many extra rounds.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.options.retries = retries;
+        self
+    }
+
+    /// Wait before the first retry round, doubled for each later round. Defaults to 250 ms.
+    pub fn retry_backoff(mut self, backoff: Duration) -> Self {
+        self.options.retry_backoff = backoff;
+        self
+    }
+
+    /// End the scan once no new host has answered for this long after the last request.
+    pub fn idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.options.idle_timeout = Some(idle_timeout);
+        self
+    }
+
+    /// Never listen longer than this after the last request.
+    pub fn max_wait(mut self, max_wait: Duration) -> Self {
+        self.options.max_wait = Some(max_wait);
+        self
+    }
+
+    /// Send at most this many requests per second.
+    pub fn rate(mut self, rate: Option<u32>) -> Self {
+        self.options.rate = rate;
+        self
+    }
+
+    /// Keep the requests under this many bits per second, counting Ethernet
+    /// padding, preamble and inter-frame gap. Combined with [`rate`](Self::rate),
+    /// the stricter limit applies.
+    pub fn bandwidth(mut self, bandwidth: Option<u64>) -> Self {
+        self.options.bandwidth = bandwidth;
+        self
+    }
+
+    /// Send to targets in a pseudo-random order instead of sweeping the range.
+    pub fn random(mut self, random: bool) -> Self {
+        self.options.random = random;
+        self
+    }
+
+    /// Seed for the random target order, to make it reproducible. A fresh seed is
+    /// picked for every scan if none is given.
+    pub fn seed(mut self, seed: Option<u64>) -> Self {
+        self.options.seed = seed;
+        self
+    }
+
+    /// Scan on the interface with this name instead of the one that owns the
+    /// primary local IP address.
+    pub fn interface(mut self, name: Option<String>) -> Self {
+        self.options.interface = name;
+        self
+    }
+
+    pub fn build(self) -> ScanOptions {
+        self.options
+    }
+}
@@ -0,0 +1,149 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Ethernet pads frames shorter than this (excluding the FCS).
+const MIN_FRAME_LEN: usize = 60;
+/// FCS, preamble with start-of-frame delimiter, and inter-frame gap.
+const WIRE_OVERHEAD: usize = 4 + 8 + 12;
+/// Frames that may go out back to back after an idle period.
+const BURST_FRAMES: f64 = 8.0;
+/// Without a configured rate, pause briefly after this many frames.
+const UNPACED_CHUNK: u64 = 32;
+const UNPACED_PAUSE: Duration = Duration::from_micros(100);
+
+/// Bits a frame of `len` bytes occupies on the wire, including padding and framing overhead.
+pub(crate) fn wire_bits(len: usize) -> u64 {
+    (len.max(MIN_FRAME_LEN) + WIRE_OVERHEAD) as u64 * 8
+}
+
+/// Token bucket that spaces out frames to stay under a packet rate and/or a
+/// bandwidth limit.
+///
+/// Tokens are measured in seconds of sending time: each frame costs the larger
+/// of `1 / rate` and `wire_bits / bandwidth`, and the bucket refills in real time
+/// up to a small burst allowance.
+pub(crate) struct Pacer {
+    rate: Option<u32>,
+    bandwidth: Option<u64>,
+    tokens: f64,
+    last_refill: Instant,
+    started: Instant,
+    /// Frames sent since the last pause when unpaced.
+    run: u64,
+    frames: u64,
+    bits: u64,
+}
+
+impl Pacer {
+    /// Creates a pacer for at most `rate` frames per second and `bandwidth` bits per
+    /// second. With neither set, frames are sent in short bursts as fast as possible.
+    pub(crate) fn new(rate: Option<u32>, bandwidth: Option<u64>) -> Self {
+        let now = Instant::now();
+        Self {
+            rate,
+            bandwidth,
+            tokens: 0.0,
+            last_refill: now,
+            started: now,
+            run: 0,
+            frames: 0,
+            bits: 0,
+        }
+    }
+
+    fn is_limited(&self) -> bool {
+        self.rate.is_some() || self.bandwidth.is_some()
+    }
+
+    /// Seconds of sending time a frame of `len` bytes costs.
+    fn cost(&self, len: usize) -> f64 {
+        let per_packet = self.rate.map_or(0.0, |rate| 1.0 / rate as f64);
+        let per_bit = self.bandwidth.map_or(0.0, |bandwidth| wire_bits(len) as f64 / bandwidth as f64);
+        per_packet.max(per_bit)
+    }
+
+    /// Blocks until a frame of `len` bytes may be sent, then accounts for it.
+    pub(crate) fn wait(&mut self, len: usize) {
+        if self.is_limited() {
+            let cost = self.cost(len);
+            let now = Instant::now();
+            if self.frames == 0 {
+                // Start with a full bucket
+                self.tokens = cost * BURST_FRAMES;
+            } else {
+                let refill = now.duration_since(self.last_refill).as_secs_f64();
+                self.tokens = (self.tokens + refill).min(cost * BURST_FRAMES);
+            }
+            self.last_refill = now;
+
+            self.tokens -= cost;
+            if self.tokens < 0.0 {
+                thread::sleep(Duration::from_secs_f64(-self.tokens));
+            }
+        } else if self.run == UNPACED_CHUNK {
+            thread::sleep(UNPACED_PAUSE);
+            self.run = 0;
+        }
+
+        self.run += 1;
+        self.frames += 1;
+        self.bits += wire_bits(len);
+    }
+
+    /// Frames per second and wire bits per second achieved since the pacer was created.
+    pub(crate) fn achieved(&self) -> (f64, f64) {
+        let elapsed = self.started.elapsed().as_secs_f64().max(f64::EPSILON);
+        (self.frames as f64 / elapsed, self.bits as f64 / elapsed)
+    }
+
+    pub(crate) fn frames(&self) -> u64 {
+        self.frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Time taken to send `frames` frames of `len` bytes.
+    fn send(pacer: &mut Pacer, frames: u32, len: usize) -> Duration {
+        let start = Instant::now();
+        for _ in 0..frames {
+            pacer.wait(len);
+        }
+        start.elapsed()
+    }
+
+    #[test]
+    fn counts_padding_and_framing_on_the_wire() {
+        assert_eq!(wire_bits(42), (60 + 24) * 8);
+        assert_eq!(wire_bits(60), (60 + 24) * 8);
+        assert_eq!(wire_bits(86), (86 + 24) * 8);
+    }
+
+    #[test]
+    fn keeps_to_the_packet_rate() {
+        // After the initial burst, frames go out one millisecond apart
+        let mut pacer = Pacer::new(Some(1000), None);
+        let elapsed = send(&mut pacer, 108, 42);
+        assert!(elapsed >= Duration::from_millis(99), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(500), "{:?}", elapsed);
+        assert_eq!(pacer.frames(), 108);
+    }
+
+    #[test]
+    fn keeps_to_the_bandwidth() {
+        // A padded ARP request takes 672 bits, so this allows 1000 of them per second
+        let mut pacer = Pacer::new(None, Some(672_000));
+        let elapsed = send(&mut pacer, 108, 42);
+        assert!(elapsed >= Duration::from_millis(99), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(500), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn applies_the_stricter_limit() {
+        let mut pacer = Pacer::new(Some(1_000_000), Some(672_000));
+        let elapsed = send(&mut pacer, 58, 42);
+        assert!(elapsed >= Duration::from_millis(49), "{:?}", elapsed);
+    }
+}
@@ -0,0 +1,137 @@
+use std::io::ErrorKind;
+use std::net::IpAddr;
+use std::time::{Duration, Instant, SystemTime};
+use crate::collector::HostCollector;
+use crate::report::{HostEntry, ScanReport};
+use crate::scanner::{ArpScanner, Link};
+use crate::targets::TargetSet;
+use crate::Result;
+
+/// How often the capture file is flushed while listening, so little is lost
+/// when listening without a time limit is interrupted.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+impl ArpScanner {
+    /// Builds the host table without sending anything, only from the ARP
+    /// replies, requests and gratuitous announcements seen on the interface.
+    ///
+    /// Listens for `duration`, or until the process is stopped if it is `None`.
+    /// `on_host` is called as soon as a new IPv4 host is learned, which is the
+    /// only way to get results out of an endless listen.
+    pub fn passive<F>(&self, duration: Option<Duration>, mut on_host: F) -> Result<ScanReport>
+    where
+        F: FnMut(&HostEntry),
+    {
+        self.listen(duration, |collector, packet, seen| {
+            if let Some(IpAddr::V4(ip)) = collector.process_packet(packet, seen) {
+                if let Some(host) = collector.host_entry(ip, self.labels.as_ref(), Some(&self.interface.name)) {
+                    on_host(&host);
+                }
+            }
+        })
+    }
+
+    /// Hands every frame seen on the interface to `on_frame` along with the time
+    /// it arrived, for `duration` or forever, and reports the hosts `on_frame`
+    /// recorded in the collector.
+    pub(crate) fn listen<F>(&self, duration: Option<Duration>, mut on_frame: F) -> Result<ScanReport>
+    where
+        F: FnMut(&HostCollector, &[u8], SystemTime),
+    {
+        let started = SystemTime::now();
+        let start = Instant::now();
+        // The sender is kept open but never used
+        let Link { tx: _tx, mut rx, capture } = self.open_link()?;
+        let collector = HostCollector::new(&self.options);
+        let mut last_flush = Instant::now();
+
+        if self.options.verbose {
+            match duration {
+                Some(duration) => println!("Listening passively on {} for {:?}", self.interface.name, duration),
+                None => println!("Listening passively on {} until interrupted", self.interface.name),
+            }
+        }
+
+        loop {
+            if duration.is_some_and(|duration| start.elapsed() >= duration) {
+                break;
+            }
+            match rx.recv_frame() {
+                Ok(Some(packet)) => on_frame(&collector, packet, SystemTime::now()),
+                Ok(None) => {}
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                // Errors other than timeouts do not go away, such as the interface going down
+                Err(e) => {
+                    if let Some(capture) = &capture {
+                        capture.flush().map_err(|e| format!("Failed to write pcap file: {}", e))?;
+                    }
+                    return Err(format!("Failed to receive on {}: {}", self.interface.name, e).into());
+                }
+            }
+
+            if let Some(capture) = &capture {
+                if last_flush.elapsed() >= FLUSH_INTERVAL {
+                    capture.flush().map_err(|e| format!("Failed to write pcap file: {}", e))?;
+                    last_flush = Instant::now();
+                }
+            }
+        }
+
+        if let Some(capture) = &capture {
+            capture.flush().map_err(|e| format!("Failed to write pcap file: {}", e))?;
+        }
+        Ok(ScanReport {
+            interface: Some(self.interface.name.clone()),
+            local_ip: Some(self.local_ip),
+            targets: TargetSet::new(),
+            hosts: collector.host_entries(self.labels.as_ref(), Some(&self.interface.name)),
+            ipv6_only: collector.ipv6_only_entries(),
+            conflicts: collector.conflicts(),
+            anomalies: collector.anomalies(),
+            started,
+            duration: start.elapsed(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use pnet::datalink::NetworkInterface;
+    use pnet::util::MacAddr;
+    use super::*;
+    use crate::options::ScanOptions;
+    use crate::simulated::SimulatedLan;
+    use crate::transport::{FrameReceiver, FrameSender, Transport};
+
+    /// A link whose interface has gone down: every receive fails at once.
+    struct DownLink;
+
+    impl Transport for DownLink {
+        fn open(&self, _interface: &NetworkInterface, _read_timeout: Duration)
+            -> Result<(Box<dyn FrameSender>, Box<dyn FrameReceiver>)> {
+            Ok((Box::new(DownLink), Box::new(DownLink)))
+        }
+    }
+
+    impl FrameSender for DownLink {
+        fn send_frame(&mut self, _frame: &[u8]) -> io::Result<()> {
+            Err(io::ErrorKind::NotConnected.into())
+        }
+    }
+
+    impl FrameReceiver for DownLink {
+        fn recv_frame(&mut self) -> io::Result<Option<&[u8]>> {
+            Err(io::ErrorKind::NotConnected.into())
+        }
+    }
+
+    #[test]
+    fn stops_when_the_link_fails() {
+        let interface = SimulatedLan::interface("sim0", MacAddr::new(0x02, 0, 0, 0, 0, 0x01), "10.1.0.1/24".parse().unwrap());
+        let scanner = ArpScanner::with_transport(ScanOptions::builder().build(), interface, Box::new(DownLink)).unwrap();
+
+        let error = scanner.passive(None, |_| {}).unwrap_err();
+        assert!(error.to_string().starts_with("Failed to receive on sim0"), "{}", error);
+    }
+}
@@ -0,0 +1,513 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Link type of Ethernet frames in pcap and pcapng files.
+pub(crate) const LINKTYPE_ETHERNET: u32 = 1;
+
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 1;
+const PCAPNG_OBSOLETE_PACKET: u32 = 2;
+const PCAPNG_SIMPLE_PACKET: u32 = 3;
+const PCAPNG_ENHANCED_PACKET: u32 = 6;
+const PCAPNG_OPTION_END: u16 = 0;
+const PCAPNG_OPTION_TSRESOL: u16 = 9;
+
+/// Snap length written to new captures; ARP frames are far smaller.
+const WRITE_SNAP_LEN: u32 = 65535;
+
+/// Upper bound on a single record or block, to avoid huge allocations on corrupt files.
+const MAX_RECORD_LEN: usize = 16 * 1024 * 1024;
+
+/// An Ethernet frame read from a capture file.
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    /// Capture time as an offset from the Unix epoch.
+    pub timestamp: Duration,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Pcap { nanos: bool },
+    PcapNg,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct NgInterface {
+    link_type: u32,
+    snap_len: u32,
+    /// Timestamp units per second.
+    ticks_per_second: u64,
+}
+
+/// Reads Ethernet frames from a pcap or pcapng capture file.
+///
+/// The format and byte order are detected from the file header. Frames captured
+/// on non-Ethernet links are skipped in pcapng files and rejected in pcap files.
+pub struct PcapReader<R: Read> {
+    reader: R,
+    format: Format,
+    big_endian: bool,
+    interfaces: Vec<NgInterface>,
+}
+
+impl PcapReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> PcapReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+
+        if u32::from_le_bytes(magic) == PCAPNG_SECTION_HEADER {
+            let mut pcap = Self { reader, format: Format::PcapNg, big_endian: false, interfaces: Vec::new() };
+            pcap.read_section_header()?;
+            return Ok(pcap);
+        }
+
+        let (big_endian, nanos) = match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+            (PCAP_MAGIC_MICROS, _) => (false, false),
+            (PCAP_MAGIC_NANOS, _) => (false, true),
+            (_, PCAP_MAGIC_MICROS) => (true, false),
+            (_, PCAP_MAGIC_NANOS) => (true, true),
+            _ => return Err(invalid_data("not a pcap or pcapng file")),
+        };
+
+        // Only the link type at the end of the header is needed.
+        let mut header = [0u8; 20];
+        reader.read_exact(&mut header)?;
+        let pcap = Self { reader, format: Format::Pcap { nanos }, big_endian, interfaces: Vec::new() };
+        let link_type = pcap.u32_at(&header, 16);
+        if link_type != LINKTYPE_ETHERNET {
+            return Err(invalid_data(&format!("unsupported link type {} (only Ethernet captures are supported)", link_type)));
+        }
+        Ok(pcap)
+    }
+
+    /// Returns the next Ethernet frame, or `None` at the end of the capture.
+    pub fn next_frame(&mut self) -> io::Result<Option<CapturedFrame>> {
+        match self.format {
+            Format::Pcap { nanos } => self.next_pcap_frame(nanos),
+            Format::PcapNg => self.next_pcapng_frame(),
+        }
+    }
+
+    fn next_pcap_frame(&mut self, nanos: bool) -> io::Result<Option<CapturedFrame>> {
+        let mut header = [0u8; 16];
+        if !read_exact_or_eof(&mut self.reader, &mut header)? {
+            return Ok(None);
+        }
+
+        let seconds = self.u32_at(&header, 0) as u64;
+        let fraction = self.u32_at(&header, 4);
+        let captured_len = self.u32_at(&header, 8) as usize;
+        if captured_len > MAX_RECORD_LEN {
+            return Err(invalid_data("record length exceeds limit"));
+        }
+
+        let mut data = vec![0u8; captured_len];
+        self.reader.read_exact(&mut data)?;
+
+        let fraction = if nanos {
+            Duration::from_nanos(fraction as u64)
+        } else {
+            Duration::from_micros(fraction as u64)
+        };
+        Ok(Some(CapturedFrame { timestamp: Duration::from_secs(seconds) + fraction, data }))
+    }
+
+    fn next_pcapng_frame(&mut self) -> io::Result<Option<CapturedFrame>> {
+        loop {
+            let mut header = [0u8; 8];
+            if !read_exact_or_eof(&mut self.reader, &mut header)? {
+                return Ok(None);
+            }
+
+            let block_type = self.u32_at(&header, 0);
+            if block_type == PCAPNG_SECTION_HEADER {
+                // A new section may switch byte order and always resets the interfaces.
+                self.read_section_body(&header[4..8])?;
+                continue;
+            }
+
+            let total_len = self.u32_at(&header, 4) as usize;
+            if !(12..=MAX_RECORD_LEN).contains(&total_len) || total_len & 3 != 0 {
+                return Err(invalid_data("invalid pcapng block length"));
+            }
+            // Body plus the trailing copy of the block length.
+            let mut body = vec![0u8; total_len - 8];
+            self.reader.read_exact(&mut body)?;
+            body.truncate(total_len - 12);
+
+            match block_type {
+                PCAPNG_INTERFACE_DESCRIPTION => self.read_interface_description(&body)?,
+                PCAPNG_ENHANCED_PACKET => {
+                    if body.len() < 20 {
+                        return Err(invalid_data("truncated enhanced packet block"));
+                    }
+                    let interface_id = self.u32_at(&body, 0) as usize;
+                    let ticks = ((self.u32_at(&body, 4) as u64) << 32) | self.u32_at(&body, 8) as u64;
+                    let captured_len = self.u32_at(&body, 12) as usize;
+                    if let Some(frame) = self.ng_frame(interface_id, ticks, &body[20..], captured_len)? {
+                        return Ok(Some(frame));
+                    }
+                }
+                PCAPNG_OBSOLETE_PACKET => {
+                    if body.len() < 20 {
+                        return Err(invalid_data("truncated packet block"));
+                    }
+                    let interface_id = self.u16_at(&body, 0) as usize;
+                    let ticks = ((self.u32_at(&body, 4) as u64) << 32) | self.u32_at(&body, 8) as u64;
+                    let captured_len = self.u32_at(&body, 12) as usize;
+                    if let Some(frame) = self.ng_frame(interface_id, ticks, &body[20..], captured_len)? {
+                        return Ok(Some(frame));
+                    }
+                }
+                PCAPNG_SIMPLE_PACKET => {
+                    if body.len() < 4 {
+                        return Err(invalid_data("truncated simple packet block"));
+                    }
+                    let original_len = self.u32_at(&body, 0) as usize;
+                    let snap_len = self.interfaces.first().map_or(0, |iface| iface.snap_len as usize);
+                    let captured_len = if snap_len == 0 { original_len } else { original_len.min(snap_len) };
+                    // Simple packet blocks carry no timestamp.
+                    if let Some(frame) = self.ng_frame(0, 0, &body[4..], captured_len)? {
+                        return Ok(Some(frame));
+                    }
+                }
+                // Name resolution, statistics and custom blocks carry no frames.
+                _ => {}
+            }
+        }
+    }
+
+    fn ng_frame(&self, interface_id: usize, ticks: u64, data: &[u8], captured_len: usize) -> io::Result<Option<CapturedFrame>> {
+        let interface = self.interfaces.get(interface_id)
+            .ok_or_else(|| invalid_data("packet refers to an undefined interface"))?;
+        if captured_len > data.len() {
+            return Err(invalid_data("packet data exceeds block length"));
+        }
+        if interface.link_type != LINKTYPE_ETHERNET {
+            return Ok(None);
+        }
+
+        let seconds = ticks / interface.ticks_per_second;
+        let remainder = ticks % interface.ticks_per_second;
+        let nanos = (remainder as u128 * 1_000_000_000 / interface.ticks_per_second as u128) as u64;
+        Ok(Some(CapturedFrame {
+            timestamp: Duration::from_secs(seconds) + Duration::from_nanos(nanos),
+            data: data[..captured_len].to_vec(),
+        }))
+    }
+
+    /// Reads the rest of a section header block whose type has already been consumed.
+    fn read_section_header(&mut self) -> io::Result<()> {
+        let mut length = [0u8; 4];
+        self.reader.read_exact(&mut length)?;
+        self.read_section_body(&length)
+    }
+
+    fn read_section_body(&mut self, length: &[u8]) -> io::Result<()> {
+        let mut magic = [0u8; 4];
+        self.reader.read_exact(&mut magic)?;
+        self.big_endian = match u32::from_le_bytes(magic) {
+            PCAPNG_BYTE_ORDER_MAGIC => false,
+            _ if u32::from_be_bytes(magic) == PCAPNG_BYTE_ORDER_MAGIC => true,
+            _ => return Err(invalid_data("invalid pcapng byte-order magic")),
+        };
+
+        let total_len = self.u32_at(length, 0) as usize;
+        if !(28..=MAX_RECORD_LEN).contains(&total_len) {
+            return Err(invalid_data("invalid pcapng section header length"));
+        }
+        // Skip version, section length, options and the trailing block length.
+        let mut rest = vec![0u8; total_len - 12];
+        self.reader.read_exact(&mut rest)?;
+        self.interfaces.clear();
+        Ok(())
+    }
+
+    fn read_interface_description(&mut self, body: &[u8]) -> io::Result<()> {
+        if body.len() < 8 {
+            return Err(invalid_data("truncated interface description block"));
+        }
+        let mut interface = NgInterface {
+            link_type: self.u16_at(body, 0) as u32,
+            snap_len: self.u32_at(body, 4),
+            ticks_per_second: 1_000_000,
+        };
+
+        let mut offset = 8;
+        while offset + 4 <= body.len() {
+            let code = self.u16_at(body, offset);
+            let len = self.u16_at(body, offset + 2) as usize;
+            offset += 4;
+            if code == PCAPNG_OPTION_END || offset + len > body.len() {
+                break;
+            }
+            if code == PCAPNG_OPTION_TSRESOL && len >= 1 {
+                let resolution = body[offset];
+                let exponent = (resolution & 0x7f) as u32;
+                let ticks = if resolution & 0x80 == 0 {
+                    10u64.checked_pow(exponent)
+                } else {
+                    2u64.checked_pow(exponent)
+                };
+                interface.ticks_per_second = ticks
+                    .filter(|&ticks| ticks > 0)
+                    .ok_or_else(|| invalid_data("unsupported timestamp resolution"))?;
+            }
+            offset += (len + 3) & !3;
+        }
+
+        self.interfaces.push(interface);
+        Ok(())
+    }
+
+    fn u16_at(&self, bytes: &[u8], offset: usize) -> u16 {
+        let value = [bytes[offset], bytes[offset + 1]];
+        if self.big_endian { u16::from_be_bytes(value) } else { u16::from_le_bytes(value) }
+    }
+
+    fn u32_at(&self, bytes: &[u8], offset: usize) -> u32 {
+        let value = [bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]];
+        if self.big_endian { u32::from_be_bytes(value) } else { u32::from_le_bytes(value) }
+    }
+}
+
+impl<R: Read> Iterator for PcapReader<R> {
+    type Item = io::Result<CapturedFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame().transpose()
+    }
+}
+
+/// Writes Ethernet frames to a classic pcap file with microsecond timestamps.
+pub struct PcapWriter<W: Write> {
+    writer: W,
+}
+
+impl PcapWriter<BufWriter<File>> {
+    /// Creates (or truncates) the file at `path` and writes the pcap header.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> PcapWriter<W> {
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(&PCAP_MAGIC_MICROS.to_le_bytes());
+        header.extend_from_slice(&2u16.to_le_bytes());
+        header.extend_from_slice(&4u16.to_le_bytes());
+        header.extend_from_slice(&0i32.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&WRITE_SNAP_LEN.to_le_bytes());
+        header.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+        writer.write_all(&header)?;
+        Ok(Self { writer })
+    }
+
+    /// Appends `frame` with the given capture time.
+    pub fn write_frame(&mut self, timestamp: SystemTime, frame: &[u8]) -> io::Result<()> {
+        let since_epoch = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let captured = &frame[..frame.len().min(WRITE_SNAP_LEN as usize)];
+
+        let mut header = [0u8; 16];
+        header[0..4].copy_from_slice(&(since_epoch.as_secs() as u32).to_le_bytes());
+        header[4..8].copy_from_slice(&since_epoch.subsec_micros().to_le_bytes());
+        header[8..12].copy_from_slice(&(captured.len() as u32).to_le_bytes());
+        header[12..16].copy_from_slice(&(frame.len() as u32).to_le_bytes());
+        self.writer.write_all(&header)?;
+        self.writer.write_all(captured)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Fills `buffer` completely, returning `false` if the input ended before any byte was read.
+fn read_exact_or_eof<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(io::Error::new(ErrorKind::UnexpectedEof, "truncated capture file")),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A big-endian pcapng section: an Ethernet interface with nanosecond
+    /// timestamps, a raw IP interface, packets on both, and a simple packet.
+    const PCAPNG_BIG_ENDIAN: &[u8] = &[
+        // Section header block, version 1.0, unknown section length
+        0x0a, 0x0d, 0x0d, 0x0a, 0x00, 0x00, 0x00, 0x1c,
+        0x1a, 0x2b, 0x3c, 0x4d, 0x00, 0x01, 0x00, 0x00,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0x00, 0x00, 0x00, 0x1c,
+        // Interface 0: Ethernet, snap length 65535, if_tsresol 10^-9
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x20,
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff,
+        0x00, 0x09, 0x00, 0x01, 0x09, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20,
+        // Interface 1: raw IP, default microsecond timestamps
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x14,
+        0x00, 0x65, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x14,
+        // Enhanced packet on interface 0 at 2^32 ns
+        0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x24,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04,
+        0x00, 0x00, 0x00, 0x04, 0xde, 0xad, 0xbe, 0xef,
+        0x00, 0x00, 0x00, 0x24,
+        // Enhanced packet on interface 1, which is not Ethernet
+        0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x24,
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04,
+        0x00, 0x00, 0x00, 0x04, 0x45, 0x00, 0x00, 0x14,
+        0x00, 0x00, 0x00, 0x24,
+        // Simple packet of 6 bytes, padded to 8
+        0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x18,
+        0x00, 0x00, 0x00, 0x06, 0x01, 0x02, 0x03, 0x04,
+        0x05, 0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18,
+    ];
+
+    fn read_all(bytes: &[u8]) -> io::Result<Vec<CapturedFrame>> {
+        PcapReader::new(bytes)?.collect()
+    }
+
+    fn error_kind(bytes: &[u8]) -> ErrorKind {
+        read_all(bytes).unwrap_err().kind()
+    }
+
+    /// A classic pcap file with one 2-byte record at 5 s plus a fraction of 1000 units.
+    fn classic_pcap(magic: u32, big_endian: bool) -> Vec<u8> {
+        let u16_bytes = |value: u16| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+        let u32_bytes = |value: u32| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&u32_bytes(magic));
+        bytes.extend_from_slice(&u16_bytes(2));
+        bytes.extend_from_slice(&u16_bytes(4));
+        for field in [0, 0, 65535, LINKTYPE_ETHERNET, 5, 1000, 2, 2] {
+            bytes.extend_from_slice(&u32_bytes(field));
+        }
+        bytes.extend_from_slice(&[0xaa, 0xbb]);
+        bytes
+    }
+
+    #[test]
+    fn reads_back_written_frames() {
+        let mut bytes = Vec::new();
+        let mut writer = PcapWriter::new(&mut bytes).unwrap();
+        writer.write_frame(UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789), &[1, 2, 3]).unwrap();
+        writer.write_frame(UNIX_EPOCH + Duration::from_secs(1_700_000_001), &[0x42; 60]).unwrap();
+        writer.flush().unwrap();
+
+        let frames = read_all(&bytes).unwrap();
+        assert_eq!(frames.len(), 2);
+        // Written with microsecond resolution
+        assert_eq!(frames[0].timestamp, Duration::new(1_700_000_000, 123_456_000));
+        assert_eq!(frames[0].data, vec![1, 2, 3]);
+        assert_eq!(frames[1].timestamp, Duration::from_secs(1_700_000_001));
+        assert_eq!(frames[1].data, vec![0x42; 60]);
+    }
+
+    #[test]
+    fn reads_big_endian_pcapng() {
+        let frames = read_all(PCAPNG_BIG_ENDIAN).unwrap();
+
+        // The packet on the raw IP interface is skipped
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].timestamp, Duration::from_nanos(1 << 32));
+        assert_eq!(frames[0].data, vec![0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(frames[1].timestamp, Duration::ZERO);
+        assert_eq!(frames[1].data, vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn detects_timestamp_resolution_and_byte_order() {
+        for big_endian in [false, true] {
+            let frames = read_all(&classic_pcap(PCAP_MAGIC_MICROS, big_endian)).unwrap();
+            assert_eq!(frames[0].timestamp, Duration::new(5, 1_000_000), "big endian: {}", big_endian);
+            assert_eq!(frames[0].data, vec![0xaa, 0xbb]);
+
+            let frames = read_all(&classic_pcap(PCAP_MAGIC_NANOS, big_endian)).unwrap();
+            assert_eq!(frames[0].timestamp, Duration::new(5, 1_000), "big endian: {}", big_endian);
+            assert_eq!(frames[0].data, vec![0xaa, 0xbb]);
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_files() {
+        assert_eq!(error_kind(b"GIF89a"), ErrorKind::InvalidData);
+
+        let mut linux_cooked = classic_pcap(PCAP_MAGIC_MICROS, false);
+        linux_cooked[20..24].copy_from_slice(&113u32.to_le_bytes());
+        assert_eq!(error_kind(&linux_cooked), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_bad_lengths() {
+        // Record longer than the limit
+        let mut oversized = classic_pcap(PCAP_MAGIC_MICROS, false);
+        oversized[32..36].copy_from_slice(&(MAX_RECORD_LEN as u32 + 1).to_le_bytes());
+        assert_eq!(error_kind(&oversized), ErrorKind::InvalidData);
+
+        // Record cut short
+        let truncated = classic_pcap(PCAP_MAGIC_MICROS, false);
+        assert_eq!(error_kind(&truncated[..truncated.len() - 1]), ErrorKind::UnexpectedEof);
+
+        // Section header shorter than its fixed fields
+        let mut short_section = PCAPNG_BIG_ENDIAN[..28].to_vec();
+        short_section[7] = 0x18;
+        assert_eq!(error_kind(&short_section), ErrorKind::InvalidData);
+
+        // Block length that is not a multiple of four
+        let mut unaligned = PCAPNG_BIG_ENDIAN.to_vec();
+        unaligned[28 + 7] = 0x21;
+        assert_eq!(error_kind(&unaligned), ErrorKind::InvalidData);
+
+        // Captured length beyond the end of the block
+        let mut overlong = PCAPNG_BIG_ENDIAN.to_vec();
+        overlong[80 + 23] = 0x05;
+        assert_eq!(error_kind(&overlong), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_packets_on_undefined_interfaces() {
+        // Section header followed directly by the first enhanced packet
+        let mut bytes = PCAPNG_BIG_ENDIAN[..28].to_vec();
+        bytes.extend_from_slice(&PCAPNG_BIG_ENDIAN[80..116]);
+        assert_eq!(error_kind(&bytes), ErrorKind::InvalidData);
+
+        // A new section forgets the interfaces of the previous one
+        let mut bytes = PCAPNG_BIG_ENDIAN[..116].to_vec();
+        bytes.extend_from_slice(&PCAPNG_BIG_ENDIAN[..28]);
+        bytes.extend_from_slice(&PCAPNG_BIG_ENDIAN[80..116]);
+        let mut reader = PcapReader::new(&bytes[..]).unwrap();
+        assert!(reader.next_frame().unwrap().is_some());
+        assert_eq!(reader.next_frame().unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+}
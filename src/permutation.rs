@@ -0,0 +1,117 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// A pseudo-random ordering of `0..len` that needs no memory beyond its own state.
+///
+/// Runs a full-period linear congruential generator modulo the next power of two
+/// `m >= len` and skips values that are out of range ("cycle walking"). With `c`
+/// odd and `a ≡ 1 (mod 4)` the generator visits every residue exactly once
+/// (Hull–Dobell), so each index comes out exactly once. Because `m < 2 * len`,
+/// at most two steps are needed per index on average.
+///
+/// The low bits of such a generator follow short cycles, so each state is passed
+/// through an invertible xorshift-multiply scramble before the range check.
+/// Being a bijection on `0..m`, it keeps the "exactly once" property.
+#[derive(Debug, Clone)]
+pub(crate) struct Permutation {
+    len: u64,
+    mask: u64,
+    multiplier: u64,
+    increment: u64,
+    scrambler: u64,
+    shift: u32,
+    state: u64,
+    steps_left: u64,
+}
+
+impl Permutation {
+    pub(crate) fn new(len: u64, seed: u64) -> Self {
+        // At least 4 so that a multiplier ≡ 1 (mod 4) exists below the modulus
+        let modulus = len.max(4).next_power_of_two();
+        let mask = modulus - 1;
+
+        let mut mixer = seed;
+        let multiplier = (splitmix64(&mut mixer) & mask & !3) | 1;
+        let increment = (splitmix64(&mut mixer) & mask) | 1;
+        let scrambler = splitmix64(&mut mixer) | 1;
+        let state = splitmix64(&mut mixer) & mask;
+
+        Self {
+            len,
+            mask,
+            multiplier,
+            increment,
+            scrambler,
+            shift: (mask.count_ones() / 2).max(1),
+            state,
+            steps_left: modulus,
+        }
+    }
+
+    fn scramble(&self, value: u64) -> u64 {
+        let value = value ^ (value >> self.shift);
+        let value = value.wrapping_mul(self.scrambler) & self.mask;
+        value ^ (value >> self.shift)
+    }
+}
+
+impl Iterator for Permutation {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        while self.steps_left > 0 {
+            self.steps_left -= 1;
+            let value = self.scramble(self.state);
+            self.state = self.multiplier.wrapping_mul(self.state).wrapping_add(self.increment) & self.mask;
+            if value < self.len {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+/// One step of the SplitMix64 generator, used to spread a seed over the LCG parameters.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A seed that differs between runs, for when none is given.
+pub(crate) fn random_seed() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos());
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEEDS: [u64; 4] = [0, 1, 0x5eed, u64::MAX];
+
+    #[test]
+    fn yields_every_index_exactly_once() {
+        for len in [0, 1, 2, 3, 5, 256, 1000] {
+            for seed in SEEDS {
+                let mut indices: Vec<u64> = Permutation::new(len, seed).collect();
+                indices.sort_unstable();
+                assert_eq!(indices, (0..len).collect::<Vec<_>>(), "len {}, seed {}", len, seed);
+            }
+        }
+    }
+
+    #[test]
+    fn order_depends_only_on_seed() {
+        let order = |seed| Permutation::new(1000, seed).collect::<Vec<_>>();
+        assert_eq!(order(SEEDS[2]), order(SEEDS[2]));
+        assert_ne!(order(SEEDS[1]), order(SEEDS[2]));
+        assert_ne!(order(SEEDS[2]), (0..1000).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,203 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::{Duration, SystemTime};
+use pnet::util::MacAddr;
+use crate::targets::TargetSet;
+
+/// `mac` in the uppercase form used for display and label lookup.
+pub fn mac_string(mac: MacAddr) -> String {
+    mac.to_string().to_uppercase()
+}
+
+/// How a host was learned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HostSource {
+    /// The scanning machine's own address.
+    Local,
+    /// An ARP reply.
+    Reply,
+    /// An ARP request the host sent itself.
+    Request,
+    /// A gratuitous ARP, announcing the host's own address.
+    Gratuitous,
+}
+
+impl fmt::Display for HostSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HostSource::Local => "local",
+            HostSource::Reply => "reply",
+            HostSource::Request => "request",
+            HostSource::Gratuitous => "gratuitous",
+        })
+    }
+}
+
+/// Response times of a host, from sending a request to receiving its reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Latency {
+    pub min: Duration,
+    pub avg: Duration,
+    pub max: Duration,
+    /// Number of replies timed.
+    pub samples: u32,
+}
+
+impl Latency {
+    pub(crate) fn from_samples(samples: &[Duration]) -> Option<Self> {
+        let count = u32::try_from(samples.len()).ok().filter(|count| *count > 0)?;
+        Some(Self {
+            min: samples.iter().min().copied()?,
+            avg: samples.iter().sum::<Duration>() / count,
+            max: samples.iter().max().copied()?,
+            samples: count,
+        })
+    }
+}
+
+/// A host that answered during a scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostEntry {
+    pub ip: Ipv4Addr,
+    pub mac: MacAddr,
+    /// Label from `labels.txt`, when label lookup is enabled and the MAC is listed.
+    pub label: Option<String>,
+    /// Hostname from `labels.txt`, when one is given for the MAC.
+    pub hostname: Option<String>,
+    /// IPv6 addresses seen with the same MAC, when IPv6 discovery is enabled.
+    pub ipv6: Vec<Ipv6Addr>,
+    /// Interface the host answered on. `None` for hosts read from a capture.
+    pub interface: Option<String>,
+    /// Number of send rounds it took for the host to answer. `None` for hosts
+    /// that were not probed, such as the local machine or hosts read from a capture.
+    pub rounds: Option<u32>,
+    /// The kind of frame the host was first learned from.
+    pub source: HostSource,
+    /// How quickly the host answered, when latency measurement is enabled and
+    /// one of its replies could be matched to a request.
+    pub latency: Option<Latency>,
+}
+
+/// An IPv6 neighbour whose MAC did not answer over IPv4.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NeighborEntry {
+    pub ip: Ipv6Addr,
+    pub mac: MacAddr,
+}
+
+/// One MAC seen claiming an address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacSighting {
+    pub mac: MacAddr,
+    /// When the first and the latest frame from this MAC for the address were seen.
+    pub first_seen: SystemTime,
+    pub last_seen: SystemTime,
+    /// Number of frames from this MAC for the address.
+    pub frames: u32,
+}
+
+/// An address that more than one MAC claimed during a scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressConflict {
+    pub ip: Ipv4Addr,
+    /// Every MAC that claimed the address, in the order they were first seen.
+    /// The host table lists the first one.
+    pub macs: Vec<MacSighting>,
+}
+
+/// Why an ARP frame was not trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnomalyKind {
+    /// The Ethernet source differs from the ARP sender hardware address.
+    SourceMismatch,
+    /// A reply during a scan was addressed to another host, not to us.
+    NotForUs,
+    /// A reply during a scan came from an address that was not probed.
+    OutOfRange,
+}
+
+impl fmt::Display for AnomalyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AnomalyKind::SourceMismatch => "source-mismatch",
+            AnomalyKind::NotForUs => "not-for-us",
+            AnomalyKind::OutOfRange => "out-of-range",
+        })
+    }
+}
+
+/// ARP frames that failed validation and were left out of the host table.
+/// Identical frames are counted once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    pub sender_ip: Ipv4Addr,
+    pub sender_mac: MacAddr,
+    pub ethernet_source: MacAddr,
+    pub target_ip: Ipv4Addr,
+    /// When the first such frame was seen.
+    pub first_seen: SystemTime,
+    /// Number of such frames.
+    pub frames: u32,
+}
+
+/// One tab-separated line: the kind of anomaly, the sender, the Ethernet source
+/// if it differs, and the address the frame was for.
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\t{}\t{}", self.kind, self.sender_ip, mac_string(self.sender_mac))?;
+        if self.ethernet_source != self.sender_mac {
+            write!(f, " via {}", mac_string(self.ethernet_source))?;
+        }
+        write!(f, "\tto {}\t{} frame{}", self.target_ip, self.frames, if self.frames == 1 { "" } else { "s" })
+    }
+}
+
+/// The outcome of a single scan.
+#[derive(Debug, Clone)]
+pub struct ScanReport {
+    /// Name of the interface the scan ran on. `None` for captures read from a file.
+    pub interface: Option<String>,
+    /// Address the ARP requests were sent from. `None` for captures read from a file.
+    pub local_ip: Option<IpAddr>,
+    /// Addresses that were probed. Empty for captures read from a file.
+    pub targets: TargetSet,
+    /// Discovered hosts, sorted by IP address. Live scans include the local machine.
+    pub hosts: Vec<HostEntry>,
+    /// IPv6 neighbours that share no MAC with the IPv4 hosts, sorted by address.
+    pub ipv6_only: Vec<NeighborEntry>,
+    /// Addresses claimed by more than one MAC, sorted by address.
+    pub conflicts: Vec<AddressConflict>,
+    /// ARP frames that failed validation, in the order they were first seen.
+    pub anomalies: Vec<Anomaly>,
+    /// Wall-clock time the scan started, or the time of the first captured frame.
+    pub started: SystemTime,
+    /// How long the scan took from the first request to the end of listening, or
+    /// the time between the first and last captured frames.
+    pub duration: Duration,
+}
+
+impl ScanReport {
+    /// How long after the start of the scan `time` was, or zero if it was before.
+    pub fn time_since_start(&self, time: SystemTime) -> Duration {
+        time.duration_since(self.started).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarises_latency_samples() {
+        assert_eq!(Latency::from_samples(&[]), None);
+
+        let samples = [Duration::from_millis(2), Duration::from_millis(1), Duration::from_millis(6)];
+        assert_eq!(Latency::from_samples(&samples), Some(Latency {
+            min: Duration::from_millis(1),
+            avg: Duration::from_millis(3),
+            max: Duration::from_millis(6),
+            samples: 3,
+        }));
+    }
+}
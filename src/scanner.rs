@@ -0,0 +1,829 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use std::thread;
+use std::sync::{Arc, Mutex};
+use pnet::datalink::{self, NetworkInterface};
+use ipnetwork::{IpNetwork, Ipv6Network};
+use local_ip_address::local_ip;
+use crate::capture::CaptureFile;
+use crate::collector::HostCollector;
+use crate::frames::{self, ArpRequestBuilder};
+use crate::interfaces;
+use crate::labels::{self, Labels};
+use crate::options::ScanOptions;
+use crate::pacer::Pacer;
+use crate::permutation::{self, Permutation};
+use crate::report::{mac_string, ScanReport};
+use crate::targets::TargetSet;
+use crate::transport::{FrameReceiver, FrameSender, PnetTransport, Transport};
+use crate::Result;
+
+/// An open channel, teed into the pcap file if one is being written.
+pub(crate) struct Link {
+    pub(crate) tx: Box<dyn FrameSender>,
+    pub(crate) rx: Box<dyn FrameReceiver>,
+    pub(crate) capture: Option<CaptureFile>,
+}
+
+/// Discovers hosts on the local network by broadcasting ARP requests.
+pub struct ArpScanner {
+    pub(crate) interface: NetworkInterface,
+    pub(crate) local_ip: IpAddr,
+    pub(crate) options: ScanOptions,
+    pub(crate) labels: Option<Labels>,
+    /// Scan every IPv4 network on the interface instead of only the local address's.
+    all_networks: bool,
+    transport: Box<dyn Transport>,
+}
+
+impl ArpScanner {
+    /// Creates a scanner bound to the interface named in the options, or else to the
+    /// interface that owns the primary local IP address.
+    pub fn new(options: ScanOptions) -> Result<Self> {
+        let (interface, local_ip) = match &options.interface {
+            Some(name) => Self::find_interface_by_name(name)?,
+            None => {
+                let local_ip = local_ip()?;
+                (Self::find_interface(&local_ip)?, local_ip)
+            }
+        };
+
+        if options.verbose {
+            println!("Local IP address: {}", local_ip);
+            if options.fast_mode {
+                println!("Fast mode enabled - using shorter timeouts");
+            }
+            println!("Using interface: {}", interface.name);
+        }
+
+        Self::build(options, interface, local_ip, Box::new(PnetTransport))
+    }
+
+    /// Creates a scanner that sends and receives through `transport` on `interface`,
+    /// using the interface's first IPv4 address as the sender address.
+    pub fn with_transport(options: ScanOptions, interface: NetworkInterface, transport: Box<dyn Transport>) -> Result<Self> {
+        let local_ip = interfaces::first_ipv4(&interface)
+            .ok_or_else(|| format!("Interface {} has no IPv4 address", interface.name))?;
+
+        Self::build(options, interface, local_ip, transport)
+    }
+
+    fn build(options: ScanOptions, interface: NetworkInterface, local_ip: IpAddr, transport: Box<dyn Transport>) -> Result<Self> {
+        let labels = if options.lookup_labels {
+            Some(labels::load_labels()?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            interface,
+            local_ip,
+            options,
+            labels,
+            all_networks: false,
+            transport,
+        })
+    }
+
+    /// Creates one scanner for every interface that is up, is not loopback or
+    /// point-to-point, and has a MAC and an IPv4 address. Each scanner covers all
+    /// IPv4 networks of its interface; run them together with [`scan_all`](Self::scan_all).
+    ///
+    /// A pcap file to write is split per interface, `scan.pcap` becoming
+    /// `scan-eth0.pcap` and so on, as the scanners run in parallel.
+    pub fn all_interfaces(options: ScanOptions) -> Result<Vec<Self>> {
+        let mut scanners = Vec::new();
+        for interface in datalink::interfaces().into_iter().filter(interfaces::is_arp_capable) {
+            let Some(local_ip) = interfaces::first_ipv4(&interface) else {
+                continue;
+            };
+            if options.verbose {
+                println!("Using interface: {}", interface.name);
+            }
+            let mut options = options.clone();
+            options.write_pcap = options.write_pcap.as_deref().map(|path| interface_capture_path(path, &interface.name));
+            scanners.push(Self::build(options, interface, local_ip, Box::new(PnetTransport))?.with_all_networks());
+        }
+
+        if scanners.is_empty() {
+            return Err("No interface is usable for an ARP scan".into());
+        }
+        Ok(scanners)
+    }
+
+    /// Makes the scanner cover every IPv4 network of its interface, each probed
+    /// from the interface's own address on that network, instead of only the
+    /// network of the local address.
+    pub fn with_all_networks(mut self) -> Self {
+        self.all_networks = true;
+        self
+    }
+
+    pub fn interface(&self) -> &NetworkInterface {
+        &self.interface
+    }
+
+    pub fn local_ip(&self) -> IpAddr {
+        self.local_ip
+    }
+
+    pub fn options(&self) -> &ScanOptions {
+        &self.options
+    }
+
+    /// Labels loaded from `labels.txt`, if label lookup is enabled.
+    pub fn labels(&self) -> Option<&Labels> {
+        self.labels.as_ref()
+    }
+
+    fn find_interface(local_ip: &IpAddr) -> Result<NetworkInterface> {
+        datalink::interfaces()
+            .into_iter()
+            .find(|iface| iface.ips.iter().any(|ip| ip.ip() == *local_ip))
+            .ok_or_else(|| "Failed to find network interface".into())
+    }
+
+    /// Finds the interface called `name` and picks its first IPv4 address as the
+    /// sender address. The error lists the interfaces that could be used instead.
+    fn find_interface_by_name(name: &str) -> Result<(NetworkInterface, IpAddr)> {
+        let interfaces = datalink::interfaces();
+        let candidates = || {
+            let usable: Vec<String> = interfaces.iter()
+                .filter(|iface| interfaces::is_arp_capable(iface))
+                .filter_map(|iface| interfaces::first_ipv4(iface).map(|ip| format!("{} ({})", iface.name, ip)))
+                .collect();
+            if usable.is_empty() {
+                "none found".to_string()
+            } else {
+                usable.join(", ")
+            }
+        };
+
+        let Some(interface) = interfaces.iter().find(|iface| iface.name == name) else {
+            return Err(format!("Unknown interface '{}'. Usable interfaces: {}", name, candidates()).into());
+        };
+        let Some(local_ip) = interfaces::first_ipv4(interface) else {
+            return Err(format!("Interface '{}' has no IPv4 address. Usable interfaces: {}", name, candidates()).into());
+        };
+        if !interfaces::has_mac(interface) {
+            return Err(format!("Interface '{}' has no MAC address. Usable interfaces: {}", name, candidates()).into());
+        }
+
+        Ok((interface.clone(), local_ip))
+    }
+
+    fn create_channel(&self) -> Result<(Box<dyn FrameSender>, Box<dyn FrameReceiver>)> {
+        let read_timeout = Duration::from_millis(if self.options.fast_mode { 5 } else { 10 });
+        self.transport.open(&self.interface, read_timeout)
+    }
+
+    fn arp_request_builder(&self, source_ip: Ipv4Addr) -> Result<ArpRequestBuilder> {
+        let source_mac = self.interface.mac.ok_or("No MAC address found for interface")?;
+        Ok(ArpRequestBuilder::new(source_mac, source_ip))
+    }
+
+    /// Listens until every expected host has answered, or, once the sender has
+    /// recorded the end of its last round in `sending_finished`, until no new host
+    /// has answered for the idle timeout or the maximum wait has passed. The
+    /// receiver is handed back when the thread finishes, so it can be reused.
+    fn start_listener(&self, collector: &HostCollector, sending_finished: &Arc<Mutex<Option<Instant>>>,
+        mut rx: Box<dyn FrameReceiver>) -> thread::JoinHandle<Box<dyn FrameReceiver>> {
+        let collector = collector.clone();
+        let sending_finished = Arc::clone(sending_finished);
+        let verbose = self.options.verbose;
+        let idle_timeout = self.options.idle_timeout();
+        let max_wait = self.options.max_wait();
+
+        thread::spawn(move || {
+            let mut last_reply: Option<Instant> = None;
+
+            if verbose {
+                println!("Started listening for responses...");
+            }
+
+            loop {
+                if let Ok(Some(packet)) = rx.recv_frame() {
+                    if collector.process_packet(packet, SystemTime::now()).is_some() {
+                        last_reply = Some(Instant::now());
+                    }
+                }
+
+                if collector.all_answered() {
+                    if verbose {
+                        println!("All targets answered");
+                    }
+                    break;
+                }
+
+                let Some(finished) = *sending_finished.lock().unwrap() else {
+                    continue;
+                };
+                if finished.elapsed() >= max_wait {
+                    if verbose {
+                        println!("Stopped listening after the maximum wait of {:?}", max_wait);
+                    }
+                    break;
+                }
+                let quiet_since = last_reply.map_or(finished, |reply| reply.max(finished));
+                if quiet_since.elapsed() >= idle_timeout {
+                    break;
+                }
+            }
+
+            rx
+        })
+    }
+
+    /// Selects the addresses to scan before exclusions, in groups paired with the
+    /// address to send from: the custom targets if any were given, every IPv4
+    /// network of the interface if the scanner covers them all, or otherwise the
+    /// network attached to the local address.
+    fn selected_targets(&self, local_ip: Ipv4Addr) -> Result<Vec<(TargetSet, Ipv4Addr)>> {
+        if let Some(targets) = &self.options.targets {
+            if self.options.verbose {
+                if targets.range_count() <= 8 {
+                    println!("Using custom targets: {} ({} addresses)", targets, targets.len());
+                } else {
+                    println!("Using custom targets: {} addresses in {} ranges", targets.len(), targets.range_count());
+                }
+            }
+            return Ok(vec![(targets.clone(), local_ip)]);
+        }
+
+        let mut targets: Vec<(TargetSet, Ipv4Addr)> = Vec::new();
+        for address in &self.interface.ips {
+            let IpNetwork::V4(address) = address else {
+                continue;
+            };
+            if !self.all_networks && IpAddr::V4(address.ip()) != self.local_ip {
+                continue;
+            }
+            // Several addresses on the same network are scanned once, from the first
+            let network = TargetSet::from(*address);
+            if targets.iter().any(|(known, _)| *known == network) {
+                continue;
+            }
+            if self.options.verbose {
+                println!("Auto-detected network on {}: {}/{}", self.interface.name, address.network(), address.prefix());
+            }
+            targets.push((network, address.ip()));
+        }
+
+        if targets.is_empty() {
+            return Err("Failed to find network".into());
+        }
+        Ok(targets)
+    }
+
+    /// The selected targets without reserved and excluded addresses, checked to be
+    /// on-link if they were given explicitly.
+    fn targets(&self, local_ip: Ipv4Addr) -> Result<Vec<(TargetSet, Ipv4Addr)>> {
+        let mut targets = self.selected_targets(local_ip)?;
+
+        if !self.options.include_all {
+            let skipped = Self::remove_addresses(&mut targets, &self.reserved_addresses());
+            if self.options.verbose && skipped > 0 {
+                println!("Skipping {} network, broadcast and local addresses", skipped);
+            }
+        }
+        if let Some(exclude) = &self.options.exclude {
+            let excluded = Self::remove_addresses(&mut targets, exclude);
+            if self.options.verbose {
+                println!("Excluded {} addresses from the scan", excluded);
+            }
+        }
+
+        // An interface with nothing left to probe is fine when scanning them all
+        if targets.is_empty() && !self.all_networks {
+            return Err("No targets left to scan".into());
+        }
+        if self.options.targets.is_some() {
+            self.check_on_link(&targets)?;
+        }
+        Ok(targets)
+    }
+
+    /// Addresses that are never worth probing: our own addresses on the interface
+    /// and the network and broadcast addresses of its networks. /31 networks have
+    /// neither, as both addresses belong to hosts (RFC 3021).
+    fn reserved_addresses(&self) -> TargetSet {
+        let mut reserved = Vec::new();
+        for address in &self.interface.ips {
+            let IpNetwork::V4(address) = address else {
+                continue;
+            };
+            reserved.push((address.ip(), address.ip()));
+            if address.prefix() < 31 {
+                reserved.push((address.network(), address.network()));
+                reserved.push((address.broadcast(), address.broadcast()));
+            }
+        }
+        TargetSet::from_ranges(reserved)
+    }
+
+    /// Removes `addresses` from every group, dropping groups left empty. Returns
+    /// how many targets were removed.
+    fn remove_addresses(targets: &mut Vec<(TargetSet, Ipv4Addr)>, addresses: &TargetSet) -> u64 {
+        let mut removed = 0;
+        for (group, _) in targets.iter_mut() {
+            let remaining = group.difference(addresses);
+            removed += group.len() - remaining.len();
+            *group = remaining;
+        }
+        targets.retain(|(group, _)| !group.is_empty());
+        removed
+    }
+
+    /// Warns about targets outside every IPv4 network of the interface. ARP
+    /// requests are not routed, so such hosts can never answer. In strict mode
+    /// this is an error instead.
+    fn check_on_link(&self, targets: &[(TargetSet, Ipv4Addr)]) -> Result<()> {
+        let networks: Vec<_> = self.interface.ips.iter()
+            .filter_map(|ip| match ip {
+                IpNetwork::V4(network) => Some(*network),
+                IpNetwork::V6(_) => None,
+            })
+            .collect();
+        let on_link = networks.iter().fold(TargetSet::new(), |all, network| all.union(&TargetSet::from(*network)));
+        let off_link = targets.iter().fold(TargetSet::new(), |all, (group, _)| all.union(&group.difference(&on_link)));
+        let Some(first) = off_link.get(0) else {
+            return Ok(());
+        };
+
+        let attached = if networks.is_empty() {
+            "no IPv4 network".to_string()
+        } else {
+            networks.iter().map(|network| format!("{}/{}", network.network(), network.prefix())).collect::<Vec<_>>().join(", ")
+        };
+        let count = match off_link.len() {
+            1 => "1 target is".to_string(),
+            count => format!("{} targets are", count),
+        };
+        let message = format!("{} outside the networks attached to {} ({}), starting at {}. \
+            ARP only works on the local segment, so hosts behind a router cannot answer",
+            count, self.interface.name, attached, first);
+        if self.options.strict {
+            return Err(message.into());
+        }
+        eprintln!("Warning: {}", message);
+        Ok(())
+    }
+
+    /// Addresses of `targets` in ascending order, or permuted by `seed` if one is given.
+    fn target_order(targets: &TargetSet, seed: Option<u64>) -> Box<dyn Iterator<Item = Ipv4Addr> + '_> {
+        match seed {
+            Some(seed) => Box::new(Permutation::new(targets.len(), seed).filter_map(|index| targets.get(index))),
+            None => Box::new(targets.iter()),
+        }
+    }
+
+    /// Sends ARP requests to every target, each group from its paired sender
+    /// address, then re-sends to the ones that have not answered for each
+    /// configured retry round.
+    ///
+    /// Frames are built one at a time as they are sent, so memory use does not
+    /// depend on the number of targets.
+    fn send_requests(&self, tx: &mut dyn FrameSender, collector: &HostCollector,
+        targets: &[(TargetSet, Ipv4Addr)]) -> Result<()> {
+        let mut requests = targets.iter()
+            .map(|(group, source_ip)| Ok((group, self.arp_request_builder(*source_ip)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut backoff = self.options.retry_backoff;
+
+        let seed = self.options.random.then(|| self.options.seed.unwrap_or_else(permutation::random_seed));
+        if let (Some(seed), true) = (seed, self.options.verbose) {
+            println!("Randomising target order (seed {})", seed);
+        }
+
+        for retry in 0..=self.options.retries {
+            // Rounds count from 1; the last one saturates rather than overflow
+            let round = retry.saturating_add(1);
+            if retry > 0 {
+                if collector.all_answered() {
+                    break;
+                }
+                thread::sleep(backoff);
+                backoff = backoff.saturating_mul(2);
+                if collector.all_answered() {
+                    break;
+                }
+            }
+
+            collector.start_round(round);
+            if self.options.verbose {
+                if retry == 0 {
+                    println!("Sending ARP requests...");
+                } else if self.options.measure_latency {
+                    println!("Retry round {}: re-sending to all targets to time their replies...", retry);
+                } else {
+                    println!("Retry round {}: re-sending to {} unanswered targets...", retry, collector.outstanding());
+                }
+            }
+
+            let mut pacer = Pacer::new(self.options.rate, self.options.bandwidth);
+            for (group, builder) in &mut requests {
+                // Only retry targets that have not answered yet, unless timing every round
+                let due = |ip: &Ipv4Addr| retry == 0 || self.options.measure_latency || !collector.contains(ip);
+                for target in Self::target_order(group, seed).filter(due) {
+                    let frame = builder.frame(target);
+                    pacer.wait(frame.len());
+                    collector.record_sent(target);
+                    if let Err(e) = tx.send_frame(frame) {
+                        if self.options.verbose {
+                            eprintln!("Warning: Failed to send ARP request: {}", e);
+                        }
+                    }
+                }
+            }
+
+            if self.options.verbose {
+                let (packets_per_second, bits_per_second) = pacer.achieved();
+                println!("Sent {} requests at {:.0} packets/s ({:.1} kbit/s on the wire)",
+                    pacer.frames(), packets_per_second, bits_per_second / 1000.0);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn ipv6_networks(&self) -> Vec<Ipv6Network> {
+        self.interface.ips.iter()
+            .filter_map(|ip| match ip {
+                IpNetwork::V6(network) => Some(*network),
+                IpNetwork::V4(_) => None,
+            })
+            .collect()
+    }
+
+    /// Sends an echo request to all nodes from each of our IPv6 addresses, as an
+    /// IPv6 network is far too large to sweep. Then, for every /64 we have an
+    /// address on, solicits the EUI-64 address each IPv4 host found so far would
+    /// have there; hosts that drop pings still answer Neighbor Solicitations.
+    fn send_neighbor_discovery(&self, tx: &mut dyn FrameSender, collector: &HostCollector) -> Result<()> {
+        let source_mac = self.interface.mac.ok_or("No MAC address found for interface")?;
+        let networks = self.ipv6_networks();
+        if networks.is_empty() {
+            if self.options.verbose {
+                println!("No IPv6 address on {}, skipping neighbour discovery", self.interface.name);
+            }
+            return Ok(());
+        }
+
+        let mut requests: Vec<Vec<u8>> = networks.iter()
+            .enumerate()
+            .map(|(sequence, network)| frames::all_nodes_echo(source_mac, network.ip(), sequence as u16))
+            .collect();
+        let macs = collector.macs();
+        for network in networks.iter().filter(|network| network.prefix() == 64) {
+            for mac in macs.iter().filter(|mac| **mac != source_mac) {
+                let target = frames::eui64_address(network.network(), *mac);
+                requests.push(frames::neighbor_solicitation(source_mac, network.ip(), target));
+            }
+        }
+
+        if self.options.verbose {
+            println!("Sending {} IPv6 neighbour discovery requests...", requests.len());
+        }
+        let mut pacer = Pacer::new(self.options.rate, self.options.bandwidth);
+        for frame in &requests {
+            pacer.wait(frame.len());
+            if let Err(e) = tx.send_frame(frame) {
+                if self.options.verbose {
+                    eprintln!("Warning: Failed to send neighbour discovery request: {}", e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs a scan and returns the hosts that answered.
+    pub fn scan(&self) -> Result<ScanReport> {
+        let targets = self.scan_targets()?;
+        let Link { mut tx, rx, capture } = self.open_link()?;
+        let (report, _) = self.scan_on(&targets, tx.as_mut(), rx)?;
+
+        if let Some(capture) = &capture {
+            capture.flush().map_err(|e| format!("Failed to write pcap file: {}", e))?;
+        }
+        Ok(report)
+    }
+
+    /// The targets of a scan from the local IPv4 address.
+    pub(crate) fn scan_targets(&self) -> Result<Vec<(TargetSet, Ipv4Addr)>> {
+        let IpAddr::V4(local_ip) = self.local_ip else {
+            return Err("Local IP is not IPv4".into());
+        };
+        self.targets(local_ip)
+    }
+
+    pub(crate) fn open_link(&self) -> Result<Link> {
+        let (tx, rx) = self.create_channel()?;
+        let capture = self.options.write_pcap.as_deref().map(CaptureFile::create).transpose()?;
+        let (tx, rx) = match &capture {
+            Some(capture) => capture.tee(tx, rx),
+            None => (tx, rx),
+        };
+        Ok(Link { tx, rx, capture })
+    }
+
+    /// Scans `targets` over an open channel and returns the report along with the
+    /// receiver, so the next scan can use the same channel.
+    pub(crate) fn scan_on(&self, targets: &[(TargetSet, Ipv4Addr)], tx: &mut dyn FrameSender,
+        rx: Box<dyn FrameReceiver>) -> Result<(ScanReport, Box<dyn FrameReceiver>)> {
+        let started = SystemTime::now();
+        let start = Instant::now();
+        let all_targets = targets.iter().fold(TargetSet::new(), |all, (group, _)| all.union(group));
+        let mut local_ips: Vec<Ipv4Addr> = targets.iter().map(|(_, source_ip)| *source_ip).collect();
+        local_ips.sort_unstable();
+        local_ips.dedup();
+
+        // The local machine is added up front and never answers its own request
+        let local_targets = if self.interface.mac.is_some() {
+            local_ips.iter().filter(|ip| all_targets.contains(**ip)).count() as u64
+        } else {
+            0
+        };
+        let outstanding = all_targets.len() - local_targets;
+        let collector = HostCollector::new(&self.options).expect(all_targets.clone(), &local_ips, outstanding);
+
+        // Add local machine to discovered hosts, before a frame claiming one of
+        // its addresses can arrive
+        if let Some(local_mac) = self.interface.mac {
+            for &local_ip in &local_ips {
+                collector.insert(local_ip, local_mac);
+                if self.options.verbose {
+                    println!("Local machine: {} (MAC: {})", local_ip, mac_string(local_mac));
+                }
+            }
+            if self.options.ipv6 {
+                for network in self.ipv6_networks() {
+                    collector.insert_ipv6(network.ip(), local_mac);
+                }
+            }
+        }
+
+        let sending_finished = Arc::new(Mutex::new(None));
+        let listening_thread = self.start_listener(&collector, &sending_finished, rx);
+
+        // Let the listener finish even if sending failed part way
+        let sent = self.send_requests(tx, &collector, targets).and_then(|()| {
+            if self.options.ipv6 {
+                self.send_neighbor_discovery(tx, &collector)
+            } else {
+                Ok(())
+            }
+        });
+        *sending_finished.lock().unwrap() = Some(Instant::now());
+        let rx = listening_thread.join().unwrap();
+        sent?;
+
+        let report = ScanReport {
+            interface: Some(self.interface.name.clone()),
+            local_ip: Some(self.local_ip),
+            targets: all_targets,
+            hosts: collector.host_entries(self.labels.as_ref(), Some(&self.interface.name)),
+            ipv6_only: collector.ipv6_only_entries(),
+            conflicts: collector.conflicts(),
+            anomalies: collector.anomalies(),
+            started,
+            duration: start.elapsed(),
+        };
+        Ok((report, rx))
+    }
+
+    /// Runs the scans of `scanners` in parallel, each on its own channel, and
+    /// returns their reports in the same order.
+    pub fn scan_all(scanners: &[ArpScanner]) -> Result<Vec<ScanReport>> {
+        thread::scope(|scope| {
+            let handles: Vec<_> = scanners.iter()
+                .map(|scanner| scope.spawn(|| scanner.scan().map_err(|e| e.to_string())))
+                .collect();
+            scanners.iter()
+                .zip(handles)
+                .map(|(scanner, handle)| handle.join()
+                    .unwrap()
+                    .map_err(|e| format!("Scan on {} failed: {}", scanner.interface.name, e).into()))
+                .collect()
+        })
+    }
+}
+
+/// `path` with the interface name added to the file name, before the extension.
+fn interface_capture_path(path: &Path, interface: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, interface, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, interface),
+    };
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv6Addr;
+    use std::time::Duration;
+    use pnet::util::MacAddr;
+    use super::*;
+    use crate::options::ScanOptionsBuilder;
+    use pnet::packet::arp::ArpOperations;
+    use pnet::packet::ethernet::MutableEthernetPacket;
+    use crate::frames::{arp_frame, eui64_address};
+    use crate::report::{AnomalyKind, HostSource, NeighborEntry};
+    use crate::simulated::SimulatedLan;
+
+    const LOCAL_MAC: MacAddr = MacAddr(0x02, 0, 0, 0, 0, 0x01);
+
+    fn mac(last: u8) -> MacAddr {
+        MacAddr::new(0x02, 0, 0, 0, 0x10, last)
+    }
+
+    fn lan(hosts: &[(&str, MacAddr)]) -> SimulatedLan {
+        SimulatedLan::new(hosts.iter().map(|(ip, mac)| (ip.parse().unwrap(), *mac)).collect())
+    }
+
+    /// An interface at 10.1.0.1 on 10.1.0.0/24.
+    fn scan_interface() -> NetworkInterface {
+        SimulatedLan::interface("sim0", LOCAL_MAC, "10.1.0.1/24".parse().unwrap())
+    }
+
+    /// Scans 10.1.0.0/24 from 10.1.0.1 on a simulated LAN with these hosts.
+    fn scan(hosts: &[(&str, MacAddr)], options: ScanOptionsBuilder) -> ScanReport {
+        scan_lan(lan(hosts), scan_interface(), options)
+    }
+
+    fn scan_lan(lan: SimulatedLan, interface: NetworkInterface, options: ScanOptionsBuilder) -> ScanReport {
+        let options = options.idle_timeout(Duration::from_millis(50)).build();
+        let scanner = ArpScanner::with_transport(options, interface, Box::new(lan)).unwrap();
+        scanner.scan().unwrap()
+    }
+
+    fn addresses(report: &ScanReport) -> Vec<(Ipv4Addr, MacAddr, HostSource)> {
+        report.hosts.iter().map(|host| (host.ip, host.mac, host.source)).collect()
+    }
+
+    #[test]
+    fn reports_table_hosts_and_local_machine() {
+        let report = scan(&[("10.1.0.7", mac(7)), ("10.1.0.20", mac(20)), ("10.1.0.254", mac(254))],
+            ScanOptions::builder());
+
+        assert_eq!(addresses(&report), vec![
+            (Ipv4Addr::new(10, 1, 0, 1), LOCAL_MAC, HostSource::Local),
+            (Ipv4Addr::new(10, 1, 0, 7), mac(7), HostSource::Reply),
+            (Ipv4Addr::new(10, 1, 0, 20), mac(20), HostSource::Reply),
+            (Ipv4Addr::new(10, 1, 0, 254), mac(254), HostSource::Reply),
+        ]);
+        assert!(report.hosts.iter().all(|host| host.interface.as_deref() == Some("sim0")));
+        assert_eq!(report.hosts[1].rounds, Some(1));
+        assert_eq!(report.hosts[0].rounds, None);
+        assert!(report.conflicts.is_empty());
+        assert!(report.anomalies.is_empty());
+    }
+
+    #[test]
+    fn skips_network_broadcast_and_own_addresses() {
+        // Answers for these would show up as hosts, or as a conflict for our own address
+        let hosts = [("10.1.0.0", mac(0)), ("10.1.0.1", mac(1)), ("10.1.0.5", mac(5)), ("10.1.0.255", mac(255))];
+        let report = scan(&hosts, ScanOptions::builder());
+
+        assert_eq!(addresses(&report), vec![
+            (Ipv4Addr::new(10, 1, 0, 1), LOCAL_MAC, HostSource::Local),
+            (Ipv4Addr::new(10, 1, 0, 5), mac(5), HostSource::Reply),
+        ]);
+        assert!(report.conflicts.is_empty());
+        assert_eq!(report.targets.len(), 253);
+        assert!(!report.targets.contains(Ipv4Addr::new(10, 1, 0, 0)));
+        assert!(!report.targets.contains(Ipv4Addr::new(10, 1, 0, 1)));
+        assert!(!report.targets.contains(Ipv4Addr::new(10, 1, 0, 255)));
+    }
+
+    #[test]
+    fn include_all_probes_reserved_addresses() {
+        let hosts = [("10.1.0.0", mac(0)), ("10.1.0.5", mac(5)), ("10.1.0.255", mac(255))];
+        let report = scan(&hosts, ScanOptions::builder().include_all(true));
+
+        let ips: Vec<Ipv4Addr> = report.hosts.iter().map(|host| host.ip).collect();
+        assert_eq!(ips, vec![
+            Ipv4Addr::new(10, 1, 0, 0),
+            Ipv4Addr::new(10, 1, 0, 1),
+            Ipv4Addr::new(10, 1, 0, 5),
+            Ipv4Addr::new(10, 1, 0, 255),
+        ]);
+        assert_eq!(report.targets.len(), 256);
+    }
+
+    #[test]
+    fn exclusions_are_not_probed() {
+        let hosts = [("10.1.0.5", mac(5)), ("10.1.0.6", mac(6))];
+        let exclude = "10.1.0.6".parse().unwrap();
+        let report = scan(&hosts, ScanOptions::builder().exclude(Some(exclude)));
+
+        let ips: Vec<Ipv4Addr> = report.hosts.iter().map(|host| host.ip).collect();
+        assert_eq!(ips, vec![Ipv4Addr::new(10, 1, 0, 1), Ipv4Addr::new(10, 1, 0, 5)]);
+    }
+
+    #[test]
+    fn measures_latency_of_every_reply() {
+        let report = scan(&[("10.1.0.7", mac(7)), ("10.1.0.20", mac(20))],
+            ScanOptions::builder().measure_latency(true).retries(2));
+
+        assert_eq!(report.hosts.len(), 3);
+        assert_eq!(report.hosts[0].latency, None);
+        for host in &report.hosts[1..] {
+            // Every round probes all targets again
+            let latency = host.latency.as_ref().unwrap();
+            assert_eq!(latency.samples, 3);
+            assert!(latency.min <= latency.avg && latency.avg <= latency.max, "{:?}", latency);
+        }
+    }
+
+    #[test]
+    fn any_number_of_retries_is_accepted() {
+        // Every target answers in the first round, so no retry round is needed
+        let lan: Vec<(String, MacAddr)> = (2..=254).map(|last| (format!("10.1.0.{}", last), mac(last))).collect();
+        let hosts: Vec<(&str, MacAddr)> = lan.iter().map(|(ip, mac)| (ip.as_str(), *mac)).collect();
+        let report = scan(&hosts, ScanOptions::builder().retries(u32::MAX));
+
+        assert_eq!(report.hosts.len(), 254);
+        assert!(report.hosts.iter().skip(1).all(|host| host.rounds == Some(1)));
+    }
+
+    #[test]
+    fn names_capture_files_after_interfaces() {
+        assert_eq!(interface_capture_path(Path::new("scan.pcap"), "eth0"), PathBuf::from("scan-eth0.pcap"));
+        assert_eq!(interface_capture_path(Path::new("/tmp/arp/scan"), "wlan0"), PathBuf::from("/tmp/arp/scan-wlan0"));
+        assert_eq!(interface_capture_path(Path::new("out/a.b.pcapng"), "br-lan"), PathBuf::from("out/a.b-br-lan.pcapng"));
+    }
+
+    #[test]
+    fn discovers_ipv6_neighbours() {
+        let prefix: Ipv6Addr = "2001:db8::".parse().unwrap();
+        let link_local: Ipv6Addr = "fe80::".parse().unwrap();
+        let interface = SimulatedLan::dual_stack_interface("sim0", LOCAL_MAC, "10.1.0.1/24".parse().unwrap(),
+            "2001:db8::1/64".parse().unwrap());
+        let lan = lan(&[("10.1.0.7", mac(7))]).with_ipv6_only(vec![mac(40)]);
+        let report = scan_lan(lan, interface, ScanOptions::builder().ipv6(true));
+
+        assert_eq!(report.hosts.len(), 2);
+        assert_eq!(report.hosts[0].ipv6, vec!["2001:db8::1".parse::<Ipv6Addr>().unwrap()]);
+        // The echo to all nodes finds the link-local address, the solicitation the global one
+        assert_eq!(report.hosts[1].ipv6, vec![eui64_address(prefix, mac(7)), eui64_address(link_local, mac(7))]);
+        assert_eq!(report.ipv6_only, vec![NeighborEntry { ip: eui64_address(link_local, mac(40)), mac: mac(40) }]);
+    }
+
+    fn ip(last: u8) -> Ipv4Addr {
+        Ipv4Addr::new(10, 1, 0, last)
+    }
+
+    /// An ARP reply whose Ethernet source may differ from its sender MAC.
+    fn reply(ethernet_source: MacAddr, sender_mac: MacAddr, sender_ip: Ipv4Addr, target_ip: Ipv4Addr) -> Vec<u8> {
+        let mut frame = arp_frame(ArpOperations::Reply, sender_mac, sender_ip, target_ip);
+        MutableEthernetPacket::new(&mut frame).unwrap().set_source(ethernet_source);
+        frame
+    }
+
+    #[test]
+    fn reports_invalid_replies_as_anomalies() {
+        let outside = Ipv4Addr::new(10, 2, 0, 9);
+        let frames = vec![
+            reply(mac(66), mac(5), ip(5), ip(1)),
+            reply(mac(6), mac(6), ip(6), ip(9)),
+            reply(mac(9), mac(9), outside, ip(1)),
+        ];
+        let report = scan_lan(lan(&[("10.1.0.7", mac(7))]).with_frames(frames), scan_interface(), ScanOptions::builder());
+
+        let anomalies: Vec<_> = report.anomalies.iter().map(|anomaly| (anomaly.kind, anomaly.sender_ip)).collect();
+        assert_eq!(anomalies, vec![
+            (AnomalyKind::SourceMismatch, ip(5)),
+            (AnomalyKind::NotForUs, ip(6)),
+            (AnomalyKind::OutOfRange, outside),
+        ]);
+        assert_eq!(report.anomalies[0].ethernet_source, mac(66));
+        assert_eq!(addresses(&report), vec![
+            (ip(1), LOCAL_MAC, HostSource::Local),
+            (ip(7), mac(7), HostSource::Reply),
+        ]);
+    }
+
+    #[test]
+    fn accepts_gratuitous_and_local_frames_unchecked() {
+        let outside = Ipv4Addr::new(10, 2, 0, 8);
+        let frames = vec![
+            // Announcements are for no one and may come from anywhere
+            reply(mac(8), mac(8), outside, outside),
+            // A claim of our own address is a conflict, not a malformed frame
+            reply(mac(66), mac(67), ip(1), ip(9)),
+        ];
+        let report = scan_lan(lan(&[]).with_frames(frames), scan_interface(), ScanOptions::builder());
+
+        assert!(report.anomalies.is_empty());
+        assert_eq!(addresses(&report), vec![
+            (ip(1), LOCAL_MAC, HostSource::Local),
+            (outside, mac(8), HostSource::Gratuitous),
+        ]);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].ip, ip(1));
+        assert_eq!(report.conflicts[0].macs[1].mac, mac(67));
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+use ipnetwork::{IpNetwork, Ipv4Network, Ipv6Network};
+use pnet::datalink::NetworkInterface;
+use pnet::packet::arp::{ArpHardwareTypes, ArpOperations, ArpPacket, MutableArpPacket};
+use pnet::packet::ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket};
+use pnet::packet::icmpv6::ndp::{MutableNeighborAdvertPacket, NdpOption, NdpOptionTypes, NeighborAdvertFlags, NeighborSolicitPacket};
+use pnet::packet::icmpv6::{self, Icmpv6Packet, Icmpv6Types, MutableIcmpv6Packet};
+use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::ipv6::{Ipv6Packet, MutableIpv6Packet};
+use pnet::packet::{MutablePacket, Packet};
+use pnet::util::MacAddr;
+use crate::frames;
+use crate::transport::{FrameReceiver, FrameSender, Transport};
+use crate::Result;
+
+/// An in-memory Ethernet segment whose hosts answer ARP requests from a fixed table.
+///
+/// Frames sent through a link opened on the LAN never touch the network; every
+/// ARP request for an address in the table is answered with a reply from the
+/// matching MAC, which the link's receiver then yields. Hosts also speak IPv6
+/// with EUI-64 addresses: they answer echo requests to all nodes from their
+/// link-local address and Neighbor Solicitations for their address on any prefix.
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedLan {
+    hosts: Arc<HashMap<Ipv4Addr, MacAddr>>,
+    /// Hosts without an IPv4 address, which only answer over IPv6.
+    ipv6_only: Arc<Vec<MacAddr>>,
+    /// Frames every link receives first, sent by no request.
+    injected: Arc<Vec<Vec<u8>>>,
+}
+
+impl SimulatedLan {
+    pub fn new(hosts: HashMap<Ipv4Addr, MacAddr>) -> Self {
+        Self { hosts: Arc::new(hosts), ipv6_only: Arc::default(), injected: Arc::default() }
+    }
+
+    /// Adds hosts that only speak IPv6.
+    pub fn with_ipv6_only(mut self, macs: Vec<MacAddr>) -> Self {
+        self.ipv6_only = Arc::new(macs);
+        self
+    }
+
+    /// Makes every link opened on the LAN receive `frames` before any reply, as
+    /// if other hosts had sent them, for traffic no request would cause.
+    pub fn with_frames(mut self, frames: Vec<Vec<u8>>) -> Self {
+        self.injected = Arc::new(frames);
+        self
+    }
+
+    /// Builds an interface description for a scanner attached to this LAN.
+    pub fn interface(name: &str, mac: MacAddr, network: Ipv4Network) -> NetworkInterface {
+        NetworkInterface {
+            name: name.to_string(),
+            description: String::new(),
+            index: 0,
+            mac: Some(mac),
+            ips: vec![IpNetwork::V4(network)],
+            flags: 0,
+        }
+    }
+
+    /// Like [`interface`](Self::interface), with an IPv6 address as well.
+    pub fn dual_stack_interface(name: &str, mac: MacAddr, network: Ipv4Network, ipv6: Ipv6Network) -> NetworkInterface {
+        let mut interface = Self::interface(name, mac, network);
+        interface.ips.push(IpNetwork::V6(ipv6));
+        interface
+    }
+
+    /// MACs of every host on the LAN, each once.
+    fn macs(&self) -> Vec<MacAddr> {
+        let mut macs: Vec<MacAddr> = self.hosts.values().chain(self.ipv6_only.iter()).copied().collect();
+        macs.sort_unstable();
+        macs.dedup();
+        macs
+    }
+
+    /// Builds the replies hosts in the table would send for `request`.
+    fn replies_to(&self, request: &[u8]) -> Vec<Vec<u8>> {
+        let Some(ethernet) = EthernetPacket::new(request) else {
+            return Vec::new();
+        };
+        match ethernet.get_ethertype() {
+            EtherTypes::Arp => self.arp_reply(&ethernet).into_iter().collect(),
+            EtherTypes::Ipv6 => self.icmpv6_replies(&ethernet),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The reply to an ARP request for the address of a host in the table.
+    fn arp_reply(&self, ethernet: &EthernetPacket) -> Option<Vec<u8>> {
+        let arp = ArpPacket::new(ethernet.payload())?;
+        if arp.get_operation() != ArpOperations::Request {
+            return None;
+        }
+        let target_ip = arp.get_target_proto_addr();
+        let responder_mac = *self.hosts.get(&target_ip)?;
+
+        let mut buffer = vec![0u8; 42];
+        let mut reply = MutableEthernetPacket::new(&mut buffer)?;
+        reply.set_destination(arp.get_sender_hw_addr());
+        reply.set_source(responder_mac);
+        reply.set_ethertype(EtherTypes::Arp);
+
+        let mut arp_reply = MutableArpPacket::new(reply.payload_mut())?;
+        arp_reply.set_hardware_type(ArpHardwareTypes::Ethernet);
+        arp_reply.set_protocol_type(EtherTypes::Ipv4);
+        arp_reply.set_hw_addr_len(6);
+        arp_reply.set_proto_addr_len(4);
+        arp_reply.set_operation(ArpOperations::Reply);
+        arp_reply.set_sender_hw_addr(responder_mac);
+        arp_reply.set_sender_proto_addr(target_ip);
+        arp_reply.set_target_hw_addr(arp.get_sender_hw_addr());
+        arp_reply.set_target_proto_addr(arp.get_sender_proto_addr());
+
+        Some(buffer)
+    }
+
+    /// Echo replies from every host for an echo request to all nodes, and a
+    /// Neighbor Advertisement for a solicitation of a host's EUI-64 address.
+    fn icmpv6_replies(&self, ethernet: &EthernetPacket) -> Vec<Vec<u8>> {
+        let Some(ipv6) = Ipv6Packet::new(ethernet.payload()) else {
+            return Vec::new();
+        };
+        let Some(icmpv6) = Icmpv6Packet::new(ipv6.payload()) else {
+            return Vec::new();
+        };
+        let requester_mac = ethernet.get_source();
+        let requester_ip = ipv6.get_source();
+
+        match icmpv6.get_icmpv6_type() {
+            Icmpv6Types::EchoRequest if ipv6.get_destination() == frames::ALL_NODES => {
+                self.macs().into_iter()
+                    .map(|mac| {
+                        let source = frames::eui64_address(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0), mac);
+                        // An echo reply is the request with the type changed
+                        let mut message = icmpv6.packet().to_vec();
+                        message[0] = Icmpv6Types::EchoReply.0;
+                        icmpv6_reply(mac, source, requester_mac, requester_ip, message)
+                    })
+                    .collect()
+            }
+            Icmpv6Types::NeighborSolicit => {
+                let Some(solicitation) = NeighborSolicitPacket::new(ipv6.payload()) else {
+                    return Vec::new();
+                };
+                let target = solicitation.get_target_addr();
+                let Some(mac) = self.macs().into_iter()
+                    .find(|mac| frames::eui64_address(target, *mac) == target) else {
+                    return Vec::new();
+                };
+
+                let mut message = vec![0u8; 32];
+                let mut advert = MutableNeighborAdvertPacket::new(&mut message).unwrap();
+                advert.set_icmpv6_type(Icmpv6Types::NeighborAdvert);
+                advert.set_flags(NeighborAdvertFlags::Solicited | NeighborAdvertFlags::Override);
+                advert.set_target_addr(target);
+                advert.set_options(&[NdpOption {
+                    option_type: NdpOptionTypes::TargetLLAddr,
+                    length: 1,
+                    data: mac.octets().to_vec(),
+                }]);
+                vec![icmpv6_reply(mac, target, requester_mac, requester_ip, message)]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Wraps an ICMPv6 `message` in IPv6 and Ethernet headers and fills in its checksum.
+fn icmpv6_reply(source_mac: MacAddr, source_ip: Ipv6Addr, destination_mac: MacAddr, destination_ip: Ipv6Addr,
+    mut message: Vec<u8>) -> Vec<u8> {
+    let checksum = icmpv6::checksum(&Icmpv6Packet::new(&message).unwrap(), &source_ip, &destination_ip);
+    MutableIcmpv6Packet::new(&mut message).unwrap().set_checksum(checksum);
+
+    let mut buffer = vec![0u8; 14 + 40 + message.len()];
+    let mut ethernet = MutableEthernetPacket::new(&mut buffer).unwrap();
+    ethernet.set_destination(destination_mac);
+    ethernet.set_source(source_mac);
+    ethernet.set_ethertype(EtherTypes::Ipv6);
+
+    let mut ipv6 = MutableIpv6Packet::new(ethernet.payload_mut()).unwrap();
+    ipv6.set_version(6);
+    ipv6.set_payload_length(message.len() as u16);
+    ipv6.set_next_header(IpNextHeaderProtocols::Icmpv6);
+    ipv6.set_hop_limit(255);
+    ipv6.set_source(source_ip);
+    ipv6.set_destination(destination_ip);
+    ipv6.set_payload(&message);
+
+    buffer
+}
+
+impl Transport for SimulatedLan {
+    fn open(&self, _interface: &NetworkInterface, read_timeout: Duration)
+        -> Result<(Box<dyn FrameSender>, Box<dyn FrameReceiver>)> {
+        let (tx, rx) = mpsc::channel();
+        for frame in self.injected.iter() {
+            tx.send(frame.clone()).unwrap();
+        }
+        let sender = SimulatedSender { lan: self.clone(), replies: tx };
+        let receiver = SimulatedReceiver { replies: rx, read_timeout, frame: Vec::new() };
+        Ok((Box::new(sender), Box::new(receiver)))
+    }
+}
+
+struct SimulatedSender {
+    lan: SimulatedLan,
+    replies: Sender<Vec<u8>>,
+}
+
+impl FrameSender for SimulatedSender {
+    fn send_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        for reply in self.lan.replies_to(frame) {
+            // The receiver may already have stopped listening; the reply is then lost
+            // just as it would be on a real link.
+            let _ = self.replies.send(reply);
+        }
+        Ok(())
+    }
+}
+
+struct SimulatedReceiver {
+    replies: Receiver<Vec<u8>>,
+    read_timeout: Duration,
+    frame: Vec<u8>,
+}
+
+impl FrameReceiver for SimulatedReceiver {
+    fn recv_frame(&mut self) -> io::Result<Option<&[u8]>> {
+        match self.replies.recv_timeout(self.read_timeout) {
+            Ok(frame) => {
+                self.frame = frame;
+                Ok(Some(&self.frame))
+            }
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            // The sender has been dropped, so nothing more can arrive; behave like
+            // an idle link rather than spinning.
+            Err(RecvTimeoutError::Disconnected) => {
+                std::thread::sleep(self.read_timeout);
+                Ok(None)
+            }
+        }
+    }
+}
@@ -0,0 +1,352 @@
+use std::fmt;
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::str::FromStr;
+use ipnetwork::Ipv4Network;
+use crate::Result;
+
+/// A deduplicated set of IPv4 addresses to probe.
+///
+/// Stored as sorted, non-overlapping inclusive ranges, so even a whole /8 takes
+/// a few bytes, and addresses can be looked up by position for a random order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetSet {
+    ranges: Vec<(u32, u32)>,
+    /// Number of addresses before each range, for lookups by index.
+    offsets: Vec<u64>,
+    len: u64,
+}
+
+impl TargetSet {
+    /// An empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a set from inclusive address ranges, which may overlap or be given in
+    /// any order. Ranges whose start is above their end are ignored.
+    pub fn from_ranges<I: IntoIterator<Item = (Ipv4Addr, Ipv4Addr)>>(ranges: I) -> Self {
+        Self::from_raw(ranges.into_iter().map(|(start, end)| (u32::from(start), u32::from(end))).collect())
+    }
+
+    fn from_raw(mut ranges: Vec<(u32, u32)>) -> Self {
+        ranges.retain(|(start, end)| start <= end);
+        ranges.sort_unstable();
+
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                // Overlapping or adjacent ranges become one
+                Some(last) if u64::from(start) <= u64::from(last.1) + 1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        let mut offsets = Vec::with_capacity(merged.len());
+        let mut len = 0;
+        for (start, end) in &merged {
+            offsets.push(len);
+            len += u64::from(end - start) + 1;
+        }
+
+        Self { ranges: merged, offsets, len }
+    }
+
+    /// Reads targets from a file with one or more specifications per line,
+    /// separated by whitespace or commas. Text after `#` is a comment.
+    pub fn read_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        let mut ranges = Vec::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            for spec in line.split(|c: char| c.is_whitespace() || c == ',').filter(|spec| !spec.is_empty()) {
+                parse_spec(spec, &mut ranges)
+                    .map_err(|e| format!("{}:{}: {}", path.display(), number + 1, e))?;
+            }
+        }
+        Ok(Self::from_raw(ranges))
+    }
+
+    /// All addresses in either set.
+    pub fn union(&self, other: &TargetSet) -> TargetSet {
+        Self::from_raw(self.ranges.iter().chain(&other.ranges).copied().collect())
+    }
+
+    /// The addresses of this set that are not in `other`.
+    pub fn difference(&self, other: &TargetSet) -> TargetSet {
+        let mut remaining = Vec::with_capacity(self.ranges.len());
+        let mut excluded = other.ranges.iter().peekable();
+        for &(start, end) in &self.ranges {
+            // Skip exclusions that end before this range starts
+            while excluded.next_if(|(_, excluded_end)| *excluded_end < start).is_some() {}
+
+            let mut start = u64::from(start);
+            let end = u64::from(end);
+            for &(excluded_start, excluded_end) in excluded.clone() {
+                let (excluded_start, excluded_end) = (u64::from(excluded_start), u64::from(excluded_end));
+                if excluded_start > end {
+                    break;
+                }
+                if excluded_start > start {
+                    remaining.push((start as u32, (excluded_start - 1) as u32));
+                }
+                start = start.max(excluded_end + 1);
+            }
+            if start <= end {
+                remaining.push((start as u32, end as u32));
+            }
+        }
+        Self::from_raw(remaining)
+    }
+
+    /// Number of addresses in the set.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, ip: Ipv4Addr) -> bool {
+        let ip = u32::from(ip);
+        let after = self.ranges.partition_point(|(start, _)| *start <= ip);
+        after > 0 && ip <= self.ranges[after - 1].1
+    }
+
+    /// The address at position `index` in ascending order.
+    pub fn get(&self, index: u64) -> Option<Ipv4Addr> {
+        if index >= self.len {
+            return None;
+        }
+        let range = self.offsets.partition_point(|offset| *offset <= index) - 1;
+        Some(Ipv4Addr::from(self.ranges[range].0 + (index - self.offsets[range]) as u32))
+    }
+
+    /// The addresses in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = Ipv4Addr> + '_ {
+        self.ranges.iter().flat_map(|&(start, end)| (start..=end).map(Ipv4Addr::from))
+    }
+
+    /// Number of separate ranges the set is made of.
+    pub fn range_count(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// The set as inclusive address ranges in ascending order.
+    pub fn ranges(&self) -> impl Iterator<Item = (Ipv4Addr, Ipv4Addr)> + '_ {
+        self.ranges.iter().map(|&(start, end)| (Ipv4Addr::from(start), Ipv4Addr::from(end)))
+    }
+}
+
+impl From<Ipv4Network> for TargetSet {
+    fn from(network: Ipv4Network) -> Self {
+        Self::from_raw(vec![(u32::from(network.network()), u32::from(network.broadcast()))])
+    }
+}
+
+/// Parses one or more comma-separated target specifications: CIDR networks
+/// (`10.0.0.0/24`), single addresses (`10.0.0.1`), dash ranges
+/// (`10.0.0.10-10.0.0.50`) and octet ranges (`10.0.1-3.0-255`).
+impl FromStr for TargetSet {
+    type Err = String;
+
+    fn from_str(specs: &str) -> std::result::Result<Self, String> {
+        let mut ranges = Vec::new();
+        for spec in specs.split(',').map(str::trim).filter(|spec| !spec.is_empty()) {
+            parse_spec(spec, &mut ranges)?;
+        }
+        if ranges.is_empty() {
+            return Err("no targets given".to_string());
+        }
+        Ok(Self::from_raw(ranges))
+    }
+}
+
+/// Lists the set as ranges and single addresses, separated by commas.
+impl fmt::Display for TargetSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (start, end)) in self.ranges().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            if start == end {
+                write!(f, "{}", start)?;
+            } else {
+                write!(f, "{}-{}", start, end)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Adds the ranges covered by a single target specification to `ranges`.
+fn parse_spec(spec: &str, ranges: &mut Vec<(u32, u32)>) -> std::result::Result<(), String> {
+    if spec.contains('/') {
+        let network = Ipv4Network::from_str(spec).map_err(|e| format!("invalid network '{}': {}", spec, e))?;
+        ranges.push((u32::from(network.network()), u32::from(network.broadcast())));
+        return Ok(());
+    }
+
+    if let Ok(ip) = Ipv4Addr::from_str(spec) {
+        ranges.push((u32::from(ip), u32::from(ip)));
+        return Ok(());
+    }
+
+    if let Some((start, end)) = spec.split_once('-') {
+        if let (Ok(start), Ok(end)) = (Ipv4Addr::from_str(start), Ipv4Addr::from_str(end)) {
+            if start > end {
+                return Err(format!("invalid range '{}': start is above end", spec));
+            }
+            ranges.push((u32::from(start), u32::from(end)));
+            return Ok(());
+        }
+    }
+
+    parse_octet_ranges(spec, ranges)
+}
+
+/// Parses an address whose octets may each be a range, such as `10.0.1-3.0-255`.
+fn parse_octet_ranges(spec: &str, ranges: &mut Vec<(u32, u32)>) -> std::result::Result<(), String> {
+    let invalid = || format!("invalid target '{}'", spec);
+
+    let parts: Vec<&str> = spec.split('.').collect();
+    if parts.len() != 4 {
+        return Err(invalid());
+    }
+    let mut octets = [(0u8, 0u8); 4];
+    for (octet, part) in octets.iter_mut().zip(parts) {
+        let (low, high) = part.split_once('-').unwrap_or((part, part));
+        let low = u8::from_str(low).map_err(|_| invalid())?;
+        let high = u8::from_str(high).map_err(|_| invalid())?;
+        if low > high {
+            return Err(format!("invalid octet range '{}' in '{}'", part, spec));
+        }
+        *octet = (low, high);
+    }
+
+    // Octets after the last partial one span 0-255 and form one contiguous block
+    // with it, so only the octets before it need to be enumerated.
+    let split = octets.iter().rposition(|&octet| octet != (0, 255)).unwrap_or(0);
+    let shift = 8 * (3 - split) as u32;
+    let block = |prefix: u32| {
+        let low = prefix | (u32::from(octets[split].0) << shift);
+        let high = prefix | (u32::from(octets[split].1) << shift) | ((1u32 << shift) - 1);
+        (low, high)
+    };
+
+    let mut prefixes = vec![0u32];
+    for (position, &(low, high)) in octets[..split].iter().enumerate() {
+        let shift = 8 * (3 - position) as u32;
+        prefixes = prefixes.iter()
+            .flat_map(|prefix| (low..=high).map(move |octet| prefix | (u32::from(octet) << shift)))
+            .collect();
+    }
+    ranges.extend(prefixes.into_iter().map(block));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(specs: &str) -> TargetSet {
+        specs.parse().unwrap()
+    }
+
+    fn ip(ip: &str) -> Ipv4Addr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_octet_ranges() {
+        let set = parse("10.0.1-3.0-255");
+        assert_eq!(set.to_string(), "10.0.1.0-10.0.3.255");
+        assert_eq!(set.len(), 768);
+
+        let set = parse("1.2.3.4-5");
+        assert_eq!(set.to_string(), "1.2.3.4-1.2.3.5");
+        assert_eq!(set.len(), 2);
+
+        // A partial octet before the last one leaves gaps
+        let set = parse("10.1-2.0.7");
+        assert_eq!(set.to_string(), "10.1.0.7,10.2.0.7");
+    }
+
+    #[test]
+    fn parses_networks_addresses_and_dash_ranges() {
+        let set = parse("192.168.1.0/30, 192.168.1.9,192.168.1.20-192.168.1.22");
+        assert_eq!(set.to_string(), "192.168.1.0-192.168.1.3,192.168.1.9,192.168.1.20-192.168.1.22");
+        assert_eq!(set.len(), 8);
+    }
+
+    #[test]
+    fn covers_the_whole_address_space() {
+        let set = parse("0.0.0.0/0");
+        assert_eq!(set.len(), 1 << 32);
+        assert_eq!(set.range_count(), 1);
+        assert_eq!(set.get(0), Some(Ipv4Addr::UNSPECIFIED));
+        assert_eq!(set.get(u64::from(u32::MAX)), Some(Ipv4Addr::BROADCAST));
+        assert_eq!(set.get(1 << 32), None);
+        assert_eq!(parse("0-255.0-255.0-255.0-255"), set);
+    }
+
+    #[test]
+    fn rejects_invalid_targets() {
+        assert!("10.0.0.50-10.0.0.10".parse::<TargetSet>().is_err());
+        assert!("10.0.0.5-4".parse::<TargetSet>().is_err());
+        assert!("10.0.0.0/33".parse::<TargetSet>().is_err());
+        assert!("10.0.0".parse::<TargetSet>().is_err());
+        assert!("10.0.0.256".parse::<TargetSet>().is_err());
+        assert!(" , ".parse::<TargetSet>().is_err());
+
+        // Reversed ranges given directly are dropped
+        assert!(TargetSet::from_ranges([(ip("10.0.0.9"), ip("10.0.0.1"))]).is_empty());
+    }
+
+    #[test]
+    fn merges_overlapping_and_adjacent_ranges() {
+        let set = parse("10.0.0.10-10.0.0.19,10.0.0.0-10.0.0.9,10.0.0.15-10.0.0.30,10.0.0.32");
+        assert_eq!(set.to_string(), "10.0.0.0-10.0.0.30,10.0.0.32");
+        assert_eq!(set.range_count(), 2);
+        assert_eq!(set.len(), 32);
+
+        // Adjacent across an octet boundary and at the top of the address space
+        assert_eq!(parse("10.0.0.255,10.0.1.0").range_count(), 1);
+        assert_eq!(parse("255.255.255.254,255.255.255.255").to_string(), "255.255.255.254-255.255.255.255");
+    }
+
+    #[test]
+    fn difference_splits_and_trims_ranges() {
+        let set = parse("10.0.0.0/24");
+
+        let middle = set.difference(&parse("10.0.0.100-10.0.0.199"));
+        assert_eq!(middle.to_string(), "10.0.0.0-10.0.0.99,10.0.0.200-10.0.0.255");
+        assert_eq!(middle.len(), 156);
+
+        let ends = set.difference(&parse("10.0.0.0,10.0.0.255,9.0.0.0-10.0.0.9,10.0.0.250-11.0.0.0"));
+        assert_eq!(ends.to_string(), "10.0.0.10-10.0.0.249");
+
+        assert!(set.difference(&parse("0.0.0.0/0")).is_empty());
+        assert_eq!(set.difference(&TargetSet::new()), set);
+    }
+
+    #[test]
+    fn looks_up_addresses_by_index() {
+        let set = parse("10.0.0.1-10.0.0.3,10.0.5.0,10.1.0.0/31");
+        let all: Vec<Ipv4Addr> = set.iter().collect();
+        assert_eq!(all.len() as u64, set.len());
+        for (index, address) in all.iter().enumerate() {
+            assert_eq!(set.get(index as u64), Some(*address));
+            assert!(set.contains(*address));
+        }
+        assert_eq!(set.get(3), Some(ip("10.0.5.0")));
+        assert_eq!(set.get(set.len()), None);
+        assert!(!set.contains(ip("10.0.0.4")));
+        assert!(!set.contains(ip("10.0.0.0")));
+    }
+}
@@ -0,0 +1,73 @@
+use std::io;
+use std::time::Duration;
+use pnet::datalink::{self, Config, DataLinkReceiver, DataLinkSender, NetworkInterface};
+use crate::Result;
+
+/// The sending half of a layer 2 link.
+pub trait FrameSender: Send {
+    /// Transmits one complete Ethernet frame.
+    fn send_frame(&mut self, frame: &[u8]) -> io::Result<()>;
+}
+
+/// The receiving half of a layer 2 link.
+pub trait FrameReceiver: Send {
+    /// Waits up to the link's read timeout for the next Ethernet frame.
+    ///
+    /// Returns `Ok(None)` when the timeout expires without a frame.
+    fn recv_frame(&mut self) -> io::Result<Option<&[u8]>>;
+}
+
+/// Opens layer 2 links on an interface.
+///
+/// The scanner only talks to the network through this trait, so a scan can run
+/// against [`PnetTransport`] on a real NIC or against a [`SimulatedLan`](crate::SimulatedLan).
+pub trait Transport: Send + Sync {
+    /// Opens a link on `interface` whose receiver gives up after `read_timeout`.
+    fn open(&self, interface: &NetworkInterface, read_timeout: Duration)
+        -> Result<(Box<dyn FrameSender>, Box<dyn FrameReceiver>)>;
+}
+
+/// Raw Ethernet channels provided by `pnet::datalink`. Requires root or administrator privileges.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PnetTransport;
+
+impl Transport for PnetTransport {
+    fn open(&self, interface: &NetworkInterface, read_timeout: Duration)
+        -> Result<(Box<dyn FrameSender>, Box<dyn FrameReceiver>)> {
+        let config = Config {
+            write_buffer_size: 4096,
+            read_buffer_size: 4096,
+            read_timeout: Some(read_timeout),
+            write_timeout: None,
+            channel_type: datalink::ChannelType::Layer2,
+            bpf_fd_attempts: 1000,
+            linux_fanout: None,
+            promiscuous: true,
+        };
+
+        match datalink::channel(interface, config) {
+            Ok(datalink::Channel::Ethernet(tx, rx)) => Ok((Box::new(PnetSender(tx)), Box::new(PnetReceiver(rx)))),
+            _ => Err("Failed to create channel".into()),
+        }
+    }
+}
+
+struct PnetSender(Box<dyn DataLinkSender>);
+
+impl FrameSender for PnetSender {
+    fn send_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.0.send_to(frame, None).unwrap_or(Ok(()))
+    }
+}
+
+struct PnetReceiver(Box<dyn DataLinkReceiver>);
+
+impl FrameReceiver for PnetReceiver {
+    fn recv_frame(&mut self) -> io::Result<Option<&[u8]>> {
+        match self.0.next() {
+            Ok(frame) => Ok(Some(frame)),
+            Err(e) if matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
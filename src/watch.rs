@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::thread;
+use std::time::{Duration, Instant};
+use pnet::util::MacAddr;
+use crate::report::{mac_string, HostEntry, ScanReport};
+use crate::scanner::{ArpScanner, Link};
+use crate::Result;
+
+/// A change between two consecutive scans in watch mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchEvent {
+    /// A host answered that did not answer the previous scan.
+    Appeared(HostEntry),
+    /// A host that answered the previous scan did not answer this one.
+    Disappeared(HostEntry),
+    /// An address now answers from a different MAC.
+    MacChanged { host: HostEntry, previous_mac: MacAddr },
+    /// A MAC now answers from a different address, and no longer from its old one.
+    IpChanged { host: HostEntry, previous_ip: Ipv4Addr },
+}
+
+/// One tab-separated line per event: the kind of change, the host, and the
+/// old and new value where something changed.
+impl fmt::Display for WatchEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchEvent::Appeared(host) => write!(f, "appeared\t{}\t{}", host.ip, mac_string(host.mac))?,
+            WatchEvent::Disappeared(host) => write!(f, "disappeared\t{}\t{}", host.ip, mac_string(host.mac))?,
+            WatchEvent::MacChanged { host, previous_mac } => write!(f, "mac-changed\t{}\t{} -> {}",
+                host.ip, mac_string(*previous_mac), mac_string(host.mac))?,
+            WatchEvent::IpChanged { host, previous_ip } => write!(f, "ip-changed\t{}\t{} -> {}",
+                mac_string(host.mac), previous_ip, host.ip)?,
+        }
+        let host = match self {
+            WatchEvent::Appeared(host) | WatchEvent::Disappeared(host) => host,
+            WatchEvent::MacChanged { host, .. } | WatchEvent::IpChanged { host, .. } => host,
+        };
+        if let Some(label) = &host.label {
+            write!(f, "\t{}", label)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the hosts of two consecutive scans.
+///
+/// A MAC that left one address and turned up at another is reported as a single
+/// address change rather than a host disappearing and another appearing.
+pub fn diff_hosts(previous: &[HostEntry], current: &[HostEntry]) -> Vec<WatchEvent> {
+    let previous_by_ip: HashMap<Ipv4Addr, &HostEntry> = previous.iter().map(|host| (host.ip, host)).collect();
+    let current_by_ip: HashMap<Ipv4Addr, &HostEntry> = current.iter().map(|host| (host.ip, host)).collect();
+
+    // Addresses that stopped answering, by the MAC they had
+    let mut vanished: HashMap<MacAddr, Vec<&HostEntry>> = HashMap::new();
+    for host in previous.iter().filter(|host| !current_by_ip.contains_key(&host.ip)) {
+        vanished.entry(host.mac).or_default().push(host);
+    }
+
+    let mut events = Vec::new();
+    for host in current {
+        match previous_by_ip.get(&host.ip) {
+            Some(old) if old.mac != host.mac => events.push(WatchEvent::MacChanged {
+                host: host.clone(),
+                previous_mac: old.mac,
+            }),
+            Some(_) => {}
+            None => match vanished.get_mut(&host.mac).and_then(|hosts| hosts.pop()) {
+                Some(old) => events.push(WatchEvent::IpChanged { host: host.clone(), previous_ip: old.ip }),
+                None => events.push(WatchEvent::Appeared(host.clone())),
+            },
+        }
+    }
+
+    let mut disappeared: Vec<&HostEntry> = vanished.into_values().flatten().collect();
+    disappeared.sort_by_key(|host| host.ip);
+    events.extend(disappeared.into_iter().map(|host| WatchEvent::Disappeared(host.clone())));
+    events
+}
+
+impl ArpScanner {
+    /// Scans again every `interval` over one channel that stays open.
+    ///
+    /// `on_scan` is called with every report and the changes since the previous
+    /// one; the first call has no events. Watching stops, without error, as soon
+    /// as `on_scan` returns `false`.
+    pub fn watch<F>(&self, interval: Duration, mut on_scan: F) -> Result<()>
+    where
+        F: FnMut(&ScanReport, &[WatchEvent]) -> bool,
+    {
+        let targets = self.scan_targets()?;
+        let Link { mut tx, mut rx, capture } = self.open_link()?;
+        let mut previous: Option<Vec<HostEntry>> = None;
+
+        loop {
+            let started = Instant::now();
+            let (report, returned_rx) = self.scan_on(&targets, tx.as_mut(), rx)?;
+            rx = returned_rx;
+            if let Some(capture) = &capture {
+                capture.flush().map_err(|e| format!("Failed to write pcap file: {}", e))?;
+            }
+
+            let events = match &previous {
+                Some(previous) => diff_hosts(previous, &report.hosts),
+                None => Vec::new(),
+            };
+            if !on_scan(&report, &events) {
+                return Ok(());
+            }
+            previous = Some(report.hosts);
+
+            thread::sleep(interval.saturating_sub(started.elapsed()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::HostSource;
+
+    fn host(last: u8, mac_last: u8) -> HostEntry {
+        HostEntry {
+            ip: Ipv4Addr::new(10, 1, 0, last),
+            mac: MacAddr::new(0x02, 0, 0, 0, 0x10, mac_last),
+            label: None,
+            hostname: None,
+            ipv6: Vec::new(),
+            interface: None,
+            rounds: Some(1),
+            source: HostSource::Reply,
+            latency: None,
+        }
+    }
+
+    #[test]
+    fn reports_nothing_for_the_same_hosts() {
+        let hosts = [host(5, 5), host(6, 6)];
+        assert_eq!(diff_hosts(&hosts, &hosts), vec![]);
+    }
+
+    #[test]
+    fn reports_appeared_and_disappeared_hosts() {
+        let events = diff_hosts(&[host(5, 5), host(6, 6)], &[host(6, 6), host(7, 7)]);
+        assert_eq!(events, vec![WatchEvent::Appeared(host(7, 7)), WatchEvent::Disappeared(host(5, 5))]);
+    }
+
+    #[test]
+    fn tells_mac_changes_from_address_moves() {
+        // The address keeps answering, from another MAC
+        assert_eq!(diff_hosts(&[host(5, 5)], &[host(5, 66)]),
+            vec![WatchEvent::MacChanged { host: host(5, 66), previous_mac: host(5, 5).mac }]);
+
+        // The MAC left its address for another one
+        assert_eq!(diff_hosts(&[host(5, 5)], &[host(9, 5)]),
+            vec![WatchEvent::IpChanged { host: host(9, 5), previous_ip: host(5, 5).ip }]);
+
+        // The MAC also answers elsewhere, but its old address is still taken
+        assert_eq!(diff_hosts(&[host(5, 5)], &[host(5, 66), host(9, 5)]), vec![
+            WatchEvent::MacChanged { host: host(5, 66), previous_mac: host(5, 5).mac },
+            WatchEvent::Appeared(host(9, 5)),
+        ]);
+    }
+
+    #[test]
+    fn moves_one_of_several_vanished_addresses_of_a_mac() {
+        let previous = [host(5, 5), host(6, 5), host(7, 5)];
+        let events = diff_hosts(&previous, &[host(9, 5)]);
+
+        assert_eq!(events, vec![
+            WatchEvent::IpChanged { host: host(9, 5), previous_ip: host(7, 5).ip },
+            WatchEvent::Disappeared(host(5, 5)),
+            WatchEvent::Disappeared(host(6, 5)),
+        ]);
+    }
+
+    #[test]
+    fn lists_disappeared_hosts_by_address() {
+        let previous = [host(200, 1), host(3, 2), host(40, 1), host(7, 3)];
+        let events = diff_hosts(&previous, &[]);
+
+        let addresses: Vec<u8> = events.iter()
+            .map(|event| match event {
+                WatchEvent::Disappeared(host) => host.ip.octets()[3],
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+        assert_eq!(addresses, vec![3, 7, 40, 200]);
+    }
+}